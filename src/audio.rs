@@ -27,7 +27,7 @@ impl AudioPlayer {
     }
 
     pub fn play(&self) {
-        if let Ok(mut sink) = self.sink.lock() {
+        if let Ok(sink) = self.sink.lock() {
             if sink.empty() { // Verifica si el audio terminó
                 self.restart();
             }
@@ -38,7 +38,7 @@ impl AudioPlayer {
     }
 
     pub fn pause(&self) {
-        if let Ok(mut sink) = self.sink.lock() {
+        if let Ok(sink) = self.sink.lock() {
             sink.pause();
         } else {
             eprintln!("Failed to lock the sink to stop playback.");
@@ -46,7 +46,7 @@ impl AudioPlayer {
     }
 
     pub fn set_volume(&self, volume: f32) {
-        if let Ok(mut sink) = self.sink.lock() {
+        if let Ok(sink) = self.sink.lock() {
             sink.set_volume(volume);
         } else {
             eprintln!("Failed to lock the sink to set volume.");
@@ -54,11 +54,16 @@ impl AudioPlayer {
     }
 
     fn restart(&self) {
-        if let Ok(mut sink) = self.sink.lock() {
+        if let Ok(sink) = self.sink.lock() {
             sink.stop(); // Detén el audio actual
-            let file = BufReader::new(File::open(&self.audio_file).unwrap());
-            let source = Decoder::new(file).unwrap().repeat_infinite(); // Repite el audio infinitamente
-            sink.append(source);
+            // Si el archivo desapareció mientras sonaba, se sigue sin música
+            let source = File::open(&self.audio_file)
+                .map_err(|err| err.to_string())
+                .and_then(|file| Decoder::new(BufReader::new(file)).map_err(|err| err.to_string()));
+            match source {
+                Ok(source) => sink.append(source.repeat_infinite()), // Repite el audio infinitamente
+                Err(err) => eprintln!("Cannot restart '{}': {}", self.audio_file, err),
+            }
         }
     }
 }
@@ -0,0 +1,218 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::maze::{self, Level, MazeError};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    name: String,
+    levels: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Progress {
+    unlocked: usize,
+}
+
+#[derive(Debug)]
+pub enum CampaignError {
+    Io { path: String, source: io::Error },
+    InvalidManifest { path: String, message: String },
+    NoLevels { path: String },
+}
+
+impl fmt::Display for CampaignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CampaignError::Io { path, source } => write!(f, "cannot read campaign file '{}': {}", path, source),
+            CampaignError::InvalidManifest { path, message } => {
+                write!(f, "invalid campaign manifest '{}': {}", path, message)
+            }
+            CampaignError::NoLevels { path } => write!(f, "campaign '{}' lists no levels", path),
+        }
+    }
+}
+
+impl std::error::Error for CampaignError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CampaignError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Lista ordenada de niveles y cuántos ha desbloqueado el jugador.
+/// Las rutas de los niveles son relativas al directorio del manifiesto.
+pub struct Campaign {
+    pub name: String,
+    pub current: usize,
+    levels: Vec<PathBuf>,
+    unlocked: usize,
+    progress_file: PathBuf,
+}
+
+impl Campaign {
+    pub fn load(manifest_path: &str, progress_path: &str) -> Result<Campaign, CampaignError> {
+        let text = fs::read_to_string(manifest_path).map_err(|source| CampaignError::Io {
+            path: manifest_path.to_string(),
+            source,
+        })?;
+        let manifest: Manifest = toml::from_str(&text).map_err(|err| CampaignError::InvalidManifest {
+            path: manifest_path.to_string(),
+            message: err.to_string(),
+        })?;
+
+        if manifest.levels.is_empty() {
+            return Err(CampaignError::NoLevels { path: manifest_path.to_string() });
+        }
+
+        let base = Path::new(manifest_path).parent().unwrap_or(Path::new("."));
+        let levels: Vec<PathBuf> = manifest.levels.iter().map(|level| base.join(level)).collect();
+
+        // Un archivo de progreso ausente o dañado equivale a empezar de cero
+        let unlocked = fs::read_to_string(progress_path)
+            .ok()
+            .and_then(|text| toml::from_str::<Progress>(&text).ok())
+            .map_or(1, |progress| progress.unlocked.clamp(1, levels.len()));
+
+        Ok(Campaign {
+            name: manifest.name,
+            current: unlocked - 1,
+            levels,
+            unlocked,
+            progress_file: PathBuf::from(progress_path),
+        })
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_unlocked(&self, index: usize) -> bool {
+        index < self.unlocked
+    }
+
+    pub fn level_path(&self, index: usize) -> String {
+        self.levels[index].display().to_string()
+    }
+
+    pub fn load_level(&self, index: usize) -> Result<Level, MazeError> {
+        maze::load_maze(&self.level_path(index))
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.current + 1 < self.levels.len()
+    }
+
+    /// Marca el nivel actual como superado, desbloquea el siguiente y guarda el progreso.
+    pub fn complete_current(&mut self) -> io::Result<()> {
+        let next = (self.current + 2).min(self.levels.len());
+        if next <= self.unlocked {
+            return Ok(());
+        }
+
+        self.unlocked = next;
+        let text = toml::to_string(&Progress { unlocked: self.unlocked })
+            .map_err(io::Error::other)?;
+        fs::write(&self.progress_file, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Carpeta temporal con un manifiesto de tres niveles (que no hace falta que existan).
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("maze-campaign-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("campaign.toml"), "name = \"Prueba\"\nlevels = [\"a.txt\", \"b.txt\", \"c.txt\"]\n").unwrap();
+        dir
+    }
+
+    fn load(dir: &Path) -> Campaign {
+        let manifest = dir.join("campaign.toml");
+        let progress = dir.join("progress.toml");
+        Campaign::load(manifest.to_str().unwrap(), progress.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn completing_a_level_unlocks_and_saves_the_next() {
+        let dir = scratch_dir("complete");
+        let mut campaign = load(&dir);
+        assert_eq!(campaign.current, 0);
+        assert!(!campaign.is_unlocked(1));
+
+        campaign.complete_current().unwrap();
+        assert!(campaign.is_unlocked(1));
+        assert!(!campaign.is_unlocked(2));
+        assert_eq!(fs::read_to_string(dir.join("progress.toml")).unwrap().trim(), "unlocked = 2");
+
+        // Al volver a abrir el juego se sigue desde el nivel desbloqueado
+        let campaign = load(&dir);
+        assert_eq!(campaign.current, 1);
+        assert!(campaign.is_unlocked(1));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn completing_the_last_level_unlocks_nothing_more() {
+        let dir = scratch_dir("last");
+        let mut campaign = load(&dir);
+        campaign.current = 2;
+        assert!(!campaign.has_next());
+
+        campaign.complete_current().unwrap();
+        assert!(campaign.is_unlocked(2));
+        assert_eq!(fs::read_to_string(dir.join("progress.toml")).unwrap().trim(), "unlocked = 3");
+
+        // Repetir un nivel ya superado no vuelve a escribir
+        fs::remove_file(dir.join("progress.toml")).unwrap();
+        campaign.current = 0;
+        campaign.complete_current().unwrap();
+        assert!(!dir.join("progress.toml").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saved_progress_past_the_last_level_is_clamped() {
+        let dir = scratch_dir("clamp");
+        fs::write(dir.join("progress.toml"), "unlocked = 99\n").unwrap();
+
+        let campaign = load(&dir);
+        assert_eq!(campaign.current, 2);
+        assert!(campaign.is_unlocked(2));
+
+        fs::write(dir.join("progress.toml"), "unlocked = 0\n").unwrap();
+        assert_eq!(load(&dir).current, 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_or_unreadable_progress_starts_over() {
+        let dir = scratch_dir("corrupt");
+        fs::write(dir.join("progress.toml"), "unlocked = \"mucho\"\n").unwrap();
+        assert_eq!(load(&dir).current, 0);
+
+        fs::write(dir.join("progress.toml"), [0xFF, 0xFE, 0x00]).unwrap();
+        assert_eq!(load(&dir).current, 0);
+
+        // Un directorio en lugar del archivo: no se puede leer ni guardar
+        fs::remove_file(dir.join("progress.toml")).unwrap();
+        fs::create_dir(dir.join("progress.toml")).unwrap();
+        let mut campaign = load(&dir);
+        assert_eq!(campaign.current, 0);
+        assert!(campaign.complete_current().is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+//! Capturas, nombres de grabaciones y medición del renderizador sin ventana. El binario solo
+//! decide cuándo llamarlas (F12, F10, `--screenshot-after`, `--bench-frames`).
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::framebuffer::Framebuffer;
+use crate::game::{Game, GameState};
+use crate::recorder::RecordFormat;
+
+/// Milisegundos desde 1970, para nombrar capturas y grabaciones sin que se pisen.
+pub fn timestamp() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+/// Ruta de una captura sin nombre pedido, dentro de screenshots/.
+pub fn screenshot_path(stamp: u128) -> String {
+    format!("screenshots/captura-{}.png", stamp)
+}
+
+/// Ruta de una grabación en recordings/: un .gif o una carpeta de PNG numerados.
+pub fn recording_path(format: RecordFormat, stamp: u128) -> String {
+    match format {
+        RecordFormat::Gif => format!("recordings/partida-{}.gif", stamp),
+        RecordFormat::Png => format!("recordings/partida-{}", stamp),
+    }
+}
+
+/// Guarda el frame en `path` (PPM si termina en .ppm, si no PNG) o, sin ruta, en
+/// screenshots/ con la hora en milisegundos como nombre. Devuelve la ruta escrita.
+pub fn save_screenshot(framebuffer: &Framebuffer, path: Option<&str>) -> Result<String, String> {
+    let path = match path {
+        Some(path) => path.to_string(),
+        None => {
+            std::fs::create_dir_all("screenshots").map_err(|err| err.to_string())?;
+            screenshot_path(timestamp())
+        }
+    };
+
+    if path.ends_with(".ppm") {
+        framebuffer.save_ppm(&path).map_err(|err| err.to_string())?;
+    } else {
+        framebuffer.save_png(&path).map_err(|err| err.to_string())?;
+    }
+    Ok(path)
+}
+
+/// Juega `frames` frames (al menos uno) sin ventana; el último queda en `framebuffer`.
+pub fn play_headless(game: &mut Game, framebuffer: &mut Framebuffer, frames: usize) {
+    game.enter(GameState::Playing);
+
+    for _ in 0..frames.max(1) {
+        framebuffer.clear();
+        game.update();
+        game.render(framebuffer);
+    }
+}
+
+/// Resultado de `bench`.
+#[derive(Clone, Copy, Debug)]
+pub struct Bench {
+    pub frames: usize,
+    pub width: usize,
+    pub height: usize,
+    pub elapsed: Duration,
+}
+
+impl Bench {
+    pub fn ms_per_frame(&self) -> f64 {
+        self.elapsed.as_secs_f64() * 1000.0 / self.frames as f64
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl fmt::Display for Bench {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames a {}x{}: {:.2} ms por frame, {:.1} FPS",
+            self.frames,
+            self.width,
+            self.height,
+            self.ms_per_frame(),
+            self.fps()
+        )
+    }
+}
+
+/// Mide cuánto se tarda en dibujar `frames` frames (al menos uno) del nivel quieto en el punto
+/// de aparición, sin lógica de juego, para comparar el renderizador entre versiones.
+pub fn bench(game: &mut Game, framebuffer: &mut Framebuffer, frames: usize) -> Bench {
+    let frames = frames.max(1);
+    game.enter(GameState::Playing);
+
+    // El primer frame carga la fuente y los glifos del HUD; no cuenta
+    game.render(framebuffer);
+
+    let start = Instant::now();
+    for _ in 0..frames {
+        framebuffer.clear();
+        game.render(framebuffer);
+    }
+
+    Bench { frames, width: framebuffer.width, height: framebuffer.height, elapsed: start.elapsed() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{parse_maze, LevelConfig};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn src(path: &str) -> String {
+        format!("{}/src/{}", env!("CARGO_MANIFEST_DIR"), path)
+    }
+
+    /// Un pasillo cerrado con las texturas por defecto en rutas absolutas y sin música.
+    fn game() -> Game {
+        let mut level = parse_maze("+++++\n+p g+\n+++++\n").unwrap();
+        let defaults = LevelConfig::default();
+        let config = &mut level.config;
+        for (glyph, path) in &defaults.textures {
+            config.textures.insert(*glyph, src(path));
+        }
+        config.sky = src(&defaults.sky);
+        config.floor = src(&defaults.floor);
+        config.enemy_sprite = src(&defaults.enemy_sprite);
+        config.music = None;
+        Game::new(level, None, None).unwrap()
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("maze-capture-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn names_screenshots_and_recordings_by_timestamp() {
+        assert_eq!(screenshot_path(42), "screenshots/captura-42.png");
+        assert_eq!(recording_path(RecordFormat::Gif, 42), "recordings/partida-42.gif");
+        assert_eq!(recording_path(RecordFormat::Png, 42), "recordings/partida-42");
+    }
+
+    #[test]
+    fn screenshots_pick_the_format_from_the_extension() {
+        let dir = scratch_dir("formats");
+        let mut framebuffer = Framebuffer::new(4, 3);
+        framebuffer.set_background_color(0x336699);
+        framebuffer.clear();
+
+        for name in ["frame.png", "frame.ppm"] {
+            let path = dir.join(name).display().to_string();
+            assert_eq!(save_screenshot(&framebuffer, Some(&path)), Ok(path.clone()));
+            let image = image::open(&path).unwrap().to_rgb8();
+            assert_eq!(image.dimensions(), (4, 3));
+            assert_eq!(image.get_pixel(0, 0).0, [0x33, 0x66, 0x99]);
+        }
+        assert!(fs::read(dir.join("frame.ppm")).unwrap().starts_with(b"P6"));
+
+        assert!(save_screenshot(&framebuffer, Some(&dir.join("missing/frame.png").display().to_string())).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn headless_play_leaves_the_last_frame_drawn() {
+        let mut game = game();
+        let mut framebuffer = Framebuffer::new(64, 48);
+        framebuffer.set_background_color(0x333355);
+
+        play_headless(&mut game, &mut framebuffer, 0);
+        assert_eq!(game.state, GameState::Playing);
+        assert!(framebuffer.buffer.iter().any(|&pixel| pixel != 0x333355));
+    }
+
+    #[test]
+    fn bench_counts_the_measured_frames() {
+        let mut game = game();
+        let mut framebuffer = Framebuffer::new(64, 48);
+
+        let result = bench(&mut game, &mut framebuffer, 3);
+        assert_eq!((result.frames, result.width, result.height), (3, 64, 48));
+        assert!((result.ms_per_frame() * result.fps() - 1000.0).abs() < 1e-6);
+        assert!(result.to_string().starts_with("3 frames a 64x48: "));
+
+        assert_eq!(bench(&mut game, &mut framebuffer, 0).frames, 1);
+    }
+}
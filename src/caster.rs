@@ -1,55 +1,325 @@
-use crate::framebuffer::Framebuffer;
+use nalgebra_glm::Vec2;
+
+use crate::maze::{Door, DoorAxis, Level};
+use crate::maze::solve::Cell;
 use crate::player::Player;
 
+/// Cara de la celda que recibió el rayo (el eje y crece hacia abajo, así que el norte es y menor).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Face {
+    North,
+    South,
+    East,
+    West,
+}
+
 pub struct Intersect {
     pub distance: f32,
     pub impact: char,
-    pub tx: usize,
+    /// Coordenada horizontal de textura exacta en 0..1 a lo largo de la cara.
+    pub u: f32,
+    pub hit: Vec2,
+    /// Dirección del rayo al llegar a la pared; cambia si ha cruzado un portal.
+    pub dir: Vec2,
+    pub face: Face,
+    pub cell: Cell,
 }
 
-pub fn cast_ray(
-    framebuffer: &mut Framebuffer,
-    maze: &Vec<Vec<char>>,
-    player: &Player,
-    a: f32,
-    block_size: usize,
-    draw_line: bool,
-) -> Intersect {
-    let mut d = 0.0;
+/// Recorrido de un rayo por la cuadrícula celda a celda (DDA). Como iterador devuelve, en orden
+/// de distancia, cada pared que toca, hasta salir del mapa o superar `max_distance`.
+/// Al entrar en un portal sigue desde la celda pareja, así que las distancias son del camino
+/// completo aunque el punto de impacto esté al otro lado del portal.
+pub struct RayWalk<'a> {
+    level: &'a Level,
+    max_distance: f32,
+    // Origen y dirección del tramo actual, y distancia recorrida al empezarlo
+    origin: Vec2,
+    dir: Vec2,
+    start: f32,
+    row: isize,
+    col: isize,
+    step_x: isize,
+    step_y: isize,
+    // Distancia a lo largo del rayo hasta el siguiente borde de celda en cada eje
+    side_x: f32,
+    side_y: f32,
+    // Distancia a lo largo del rayo para cruzar una celda completa en cada eje
+    delta_x: f32,
+    delta_y: f32,
+    /// Distancia a la que el rayo salió del mapa o se quedó sin alcance.
+    pub end: f32,
+    done: bool,
+}
 
-    framebuffer.set_current_color(0xFFFFFF);
+impl<'a> RayWalk<'a> {
+    pub fn new(level: &'a Level, origin: Vec2, a: f32, max_distance: f32) -> Self {
+        let mut walk = RayWalk {
+            level,
+            max_distance,
+            origin,
+            dir: Vec2::new(a.cos(), a.sin()),
+            start: 0.0,
+            row: 0,
+            col: 0,
+            step_x: 0,
+            step_y: 0,
+            side_x: 0.0,
+            side_y: 0.0,
+            delta_x: 0.0,
+            delta_y: 0.0,
+            end: max_distance,
+            done: false,
+        };
+        walk.aim(origin, walk.dir, 0.0);
+        walk
+    }
 
-    loop {
-        let cos = d * a.cos();
-        let sin = d * a.sin();
+    /// Empieza un tramo nuevo desde `origin`, con `start` de distancia ya recorrida.
+    fn aim(&mut self, origin: Vec2, dir: Vec2, start: f32) {
+        let block_size = self.level.block_size as f32;
 
-        let x = (player.pos.x + cos) as usize;
-        let y = (player.pos.y + sin) as usize;
+        self.origin = origin;
+        self.dir = dir;
+        self.start = start;
+        self.col = (origin.x / block_size).floor() as isize;
+        self.row = (origin.y / block_size).floor() as isize;
 
-        let i = x / block_size;
-        let j = y / block_size;
+        self.delta_x = if dir.x == 0.0 { f32::INFINITY } else { (block_size / dir.x).abs() };
+        self.delta_y = if dir.y == 0.0 { f32::INFINITY } else { (block_size / dir.y).abs() };
 
-        let hitx = x - i * block_size;
-        let hity = y - j * block_size;
+        (self.step_x, self.side_x) = if dir.x < 0.0 {
+            (-1, start + (origin.x - self.col as f32 * block_size) / -dir.x)
+        } else {
+            (1, start + ((self.col + 1) as f32 * block_size - origin.x) / dir.x)
+        };
+        (self.step_y, self.side_y) = if dir.y < 0.0 {
+            (-1, start + (origin.y - self.row as f32 * block_size) / -dir.y)
+        } else {
+            (1, start + ((self.row + 1) as f32 * block_size - origin.y) / dir.y)
+        };
+    }
+}
 
-        let mut maxhit = hity;
+impl Iterator for RayWalk<'_> {
+    type Item = Intersect;
 
-        if 1 < hitx && hitx < block_size - 1 {
-            maxhit = hitx;
-        }
+    fn next(&mut self) -> Option<Intersect> {
+        let maze = &self.level.maze;
+        let block_size = self.level.block_size as f32;
+        let rows = maze.len() as isize;
 
-        if draw_line {
-            framebuffer.point(x, y);
+        if self.done {
+            return None;
         }
 
-        if maze[j][i] != ' ' {
-            return Intersect {
-                distance: d,
-                impact: maze[j][i],
-                tx: maxhit * 128 / block_size,
+        loop {
+            let x_side = self.side_x < self.side_y;
+            let distance = if x_side {
+                self.col += self.step_x;
+                let distance = self.side_x;
+                self.side_x += self.delta_x;
+                distance
+            } else {
+                self.row += self.step_y;
+                let distance = self.side_y;
+                self.side_y += self.delta_y;
+                distance
+            };
+
+            let (row, col) = (self.row, self.col);
+            let cell = (row >= 0 && row < rows && col >= 0)
+                .then(|| maze[row as usize].get(col as usize))
+                .flatten();
+            let impact = match cell {
+                _ if distance > self.max_distance => None,
+                Some(&' ') => continue,
+                Some(&impact) => Some(impact),
+                None => None,
+            };
+
+            let Some(impact) = impact else {
+                // Sin más paredes: el recorrido termina aquí y las siguientes llamadas también
+                self.end = distance.min(self.max_distance);
+                self.done = true;
+                return None;
+            };
+
+            let grid_cell = (row as usize, col as usize);
+            let entry = self.origin + self.dir * (distance - self.start);
+            if let Some((origin, dir)) = self.level.warp(grid_cell, entry, self.dir) {
+                // Un poco dentro de la celda pareja para no caer en la pared que tiene detrás
+                self.aim(origin + dir * (block_size * 0.001), dir, distance);
+                continue;
+            }
+
+            let (distance, hit, face, along) = match self.level.doors.get(&grid_cell) {
+                // La hoja de la puerta está a medio bloque; si el rayo pasa por el hueco, sigue
+                Some(door) => match door_hit(door, grid_cell, self.origin, self.dir, block_size) {
+                    Some((distance, hit, face, along)) => (self.start + distance, hit, face, along),
+                    None => continue,
+                },
+                None => {
+                    let (face, along) = match (x_side, self.step_x, self.step_y) {
+                        (true, 1, _) => (Face::West, entry.y),
+                        (true, _, _) => (Face::East, entry.y),
+                        (false, _, 1) => (Face::North, entry.x),
+                        (false, _, _) => (Face::South, entry.x),
+                    };
+                    (distance, entry, face, along / block_size)
+                }
+            };
+
+            // Invertir en dos caras para que la textura no se vea en espejo
+            let mut u = along.fract();
+            if face == Face::East || face == Face::North {
+                u = 1.0 - u;
             }
-        } 
+            let u = u.clamp(0.0, 0.999);
 
-        d += 1.5;
+            return Some(Intersect {
+                distance,
+                impact,
+                u,
+                hit,
+                dir: self.dir,
+                face,
+                cell: grid_cell,
+            });
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Punto exacto donde el rayo de ángulo `a` toca la primera pared, o `None` si sale del mapa
+/// o supera `max_distance`. No dibuja nada, así que se puede llamar desde varios hilos.
+pub fn cast_ray(level: &Level, player: &Player, a: f32, max_distance: f32) -> Option<Intersect> {
+    RayWalk::new(level, player.pos, a, max_distance).next()
+}
+
+/// Corte del rayo con la hoja de una puerta: distancia, punto, cara y coordenada a lo largo
+/// de la hoja en bloques. La parte ya deslizada dentro de la pared no cuenta.
+fn door_hit(door: &Door, cell: Cell, origin: Vec2, dir: Vec2, block_size: f32) -> Option<(f32, Vec2, Face, f32)> {
+    let (row, col) = (cell.0 as f32, cell.1 as f32);
+    let (distance, face) = match door.axis {
+        DoorAxis::EastWest if dir.y != 0.0 => {
+            let face = if dir.y > 0.0 { Face::North } else { Face::South };
+            (((row + 0.5) * block_size - origin.y) / dir.y, face)
+        }
+        DoorAxis::NorthSouth if dir.x != 0.0 => {
+            let face = if dir.x > 0.0 { Face::West } else { Face::East };
+            (((col + 0.5) * block_size - origin.x) / dir.x, face)
+        }
+        _ => return None,
+    };
+
+    let hit = origin + dir * distance;
+    let offset = match door.axis {
+        DoorAxis::EastWest => hit.x / block_size - col,
+        DoorAxis::NorthSouth => hit.y / block_size - row,
+    };
+    if distance < 0.0 || !(door.openness..1.0).contains(&offset) {
+        return None;
+    }
+
+    Some((distance, hit, face, offset - door.openness))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    fn level(text: &str) -> Level {
+        Level::new(text.lines().map(|line| line.chars().collect()).collect(), 100)
+    }
+
+    fn cast(level: &Level, x: f32, y: f32, a: f32) -> Intersect {
+        let player = Player::new(Vec2::new(x, y), a, PI / 3.0);
+        cast_ray(level, &player, a, 10_000.0).expect("the ray should hit a wall")
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.01, "{} != {}", actual, expected);
+    }
+
+    const ROOM: &str = "+--+--+\n|    g|\n+--+--+";
+
+    #[test]
+    fn east_wall() {
+        let hit = cast(&level(ROOM), 150.0, 150.0, 0.0);
+        assert_close(hit.distance, 350.0);
+        assert_eq!((hit.face, hit.impact, hit.cell), (Face::West, 'g', (1, 5)));
+        assert_close(hit.u, 0.5);
+        assert_close(hit.hit.x, 500.0);
+    }
+
+    #[test]
+    fn south_wall() {
+        let hit = cast(&level(ROOM), 150.0, 150.0, FRAC_PI_2);
+        assert_close(hit.distance, 50.0);
+        assert_eq!((hit.face, hit.impact), (Face::North, '-'));
+        assert_close(hit.u, 0.5);
+    }
+
+    #[test]
+    fn west_wall() {
+        let hit = cast(&level(ROOM), 150.0, 150.0, PI);
+        assert_close(hit.distance, 50.0);
+        assert_eq!((hit.face, hit.impact, hit.cell), (Face::East, '|', (1, 0)));
+    }
+
+    #[test]
+    fn u_follows_the_hit_along_the_face() {
+        // Un cuarto de bloque por encima del centro en la cara oeste de 'g'
+        let hit = cast(&level(ROOM), 150.0, 125.0, 0.0);
+        assert_close(hit.u, 0.25);
+        // En la cara este de '|' la textura va al revés para no verse en espejo
+        let hit = cast(&level(ROOM), 150.0, 125.0, PI);
+        assert_close(hit.u, 0.75);
+    }
+
+    #[test]
+    fn corner() {
+        // Justo hacia la esquina de arriba a la izquierda del pasillo
+        let hit = cast(&level(ROOM), 150.0, 150.0, -3.0 * FRAC_PI_4);
+        assert_close(hit.distance, 50.0 * 2f32.sqrt());
+        assert_close(hit.hit.x, 100.0);
+        assert_close(hit.hit.y, 100.0);
+        assert!(hit.cell == (0, 1) || hit.cell == (1, 0) || hit.cell == (0, 0), "{:?}", hit.cell);
+    }
+
+    #[test]
+    fn leaving_the_map() {
+        let open = level("+--+--+\n|     \n+--+--+");
+        let player = Player::new(Vec2::new(150.0, 150.0), 0.0, PI / 3.0);
+        assert!(cast_ray(&open, &player, 0.0, 10_000.0).is_none());
+        // Sin alcance tampoco hay pared
+        assert!(cast_ray(&level(ROOM), &player, 0.0, 300.0).is_none());
+    }
+
+    #[test]
+    fn doors() {
+        let mut level = level("+--+--+--+\n|   D   g|\n+--+--+--+");
+        let hit = cast(&level, 150.0, 150.0, 0.0);
+        // La hoja está a medio bloque dentro de la celda de la puerta
+        assert_close(hit.distance, 300.0);
+        assert_eq!((hit.face, hit.impact, hit.cell), (Face::West, 'D', (1, 4)));
+        assert_close(door_hit(&level.doors[&(1, 4)], (1, 4), Vec2::new(150.0, 150.0), Vec2::new(1.0, 0.0), 100.0).unwrap().0, 300.0);
+
+        // Abierta del todo, el rayo pasa hasta la salida
+        level.doors.get_mut(&(1, 4)).unwrap().openness = 1.0;
+        let hit = cast(&level, 150.0, 150.0, 0.0);
+        assert_eq!(hit.impact, 'g');
+        assert_close(hit.distance, 650.0);
+    }
+
+    #[test]
+    fn portals() {
+        let level = level("+--+--+--+\n|1       |\n+--+--+--+\n|1      g|\n+--+--+--+");
+        // Hacia el oeste por el pasillo de arriba; el portal devuelve el rayo hacia el este abajo
+        let hit = cast(&level, 450.0, 150.0, PI);
+        assert_eq!((hit.impact, hit.cell, hit.face), ('g', (3, 8), Face::West));
+        assert!(hit.dir.x > 0.99, "{:?}", hit.dir);
+        // 250 hasta el portal y 700 desde el borde de la celda pareja hasta 'g'
+        assert!((hit.distance - 950.0).abs() < 1.0, "{}", hit.distance);
+    }
+}
@@ -0,0 +1,170 @@
+/// Color RGBA de 8 bits por canal. El framebuffer guarda 0xRRGGBB opaco; el alfa solo dice
+/// cuánto cubre el color lo que ya había debajo al escribirlo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// Cómo se combina un color nuevo con el píxel que ya hay en el framebuffer. En todos los modos
+/// el alfa del color nuevo regula cuánto efecto tiene.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Mezcla normal: con alfa 255 sustituye el píxel.
+    #[default]
+    Alpha,
+    /// Suma los colores (luces, destellos); satura en blanco.
+    Additive,
+    /// Multiplica los colores (sombras, tintes); con blanco no cambia nada.
+    Multiply,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const TRANSPARENT: Color = Color::new(0, 0, 0, 0);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color::new(r, g, b, 255)
+    }
+
+    /// Color opaco a partir de 0xRRGGBB, el formato de los píxeles del framebuffer.
+    pub const fn from_hex(hex: u32) -> Color {
+        Color::rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+    }
+
+    /// 0xRRGGBB sin el alfa.
+    pub const fn to_hex(self) -> u32 {
+        ((self.r as u32) << 16) | ((self.g as u32) << 8) | self.b as u32
+    }
+
+    pub const fn with_alpha(self, a: u8) -> Color {
+        Color { a, ..self }
+    }
+
+    pub fn is_opaque(self) -> bool {
+        self.a == 255
+    }
+
+    /// Interpola canal a canal hacia `to` (t = 0 devuelve `self`); el alfa es el de `self`.
+    pub fn mix(self, to: Color, t: f32) -> Color {
+        // Fuera de 0..1 un canal se saldría de 0..255
+        let t = t.clamp(0.0, 1.0);
+        let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color::new(channel(self.r, to.r), channel(self.g, to.g), channel(self.b, to.b), self.a)
+    }
+
+    /// Multiplica el brillo de cada canal; con `factor` mayor que 1 se satura en blanco. El alfa
+    /// no cambia.
+    pub fn scale(self, factor: f32) -> Color {
+        let channel = |c: u8| (c as f32 * factor).round().min(255.0) as u8;
+        Color::new(channel(self.r), channel(self.g), channel(self.b), self.a)
+    }
+
+    /// Resultado de pintar este color encima de `below` con el modo `mode`; siempre opaco.
+    pub fn blend(self, below: Color, mode: BlendMode) -> Color {
+        let alpha = self.a as u32;
+        let channel = |top: u8, below: u8| {
+            let (top, below) = (top as u32, below as u32);
+            let target = match mode {
+                BlendMode::Alpha => top,
+                BlendMode::Additive => (below + top).min(255),
+                BlendMode::Multiply => below * top / 255,
+            };
+            ((target * alpha + below * (255 - alpha) + 127) / 255) as u8
+        };
+        Color::rgb(channel(self.r, below.r), channel(self.g, below.g), channel(self.b, below.b))
+    }
+}
+
+impl From<u32> for Color {
+    fn from(hex: u32) -> Color {
+        Color::from_hex(hex)
+    }
+}
+
+impl From<Color> for u32 {
+    fn from(color: Color) -> u32 {
+        color.to_hex()
+    }
+}
+
+impl From<image::Rgba<u8>> for Color {
+    fn from(pixel: image::Rgba<u8>) -> Color {
+        Color::new(pixel[0], pixel[1], pixel[2], pixel[3])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BELOW: Color = Color::rgb(40, 100, 200);
+
+    #[test]
+    fn alpha_blending() {
+        let red = Color::rgb(255, 0, 0);
+        assert_eq!(red.with_alpha(0).blend(BELOW, BlendMode::Alpha), BELOW);
+        assert_eq!(red.blend(BELOW, BlendMode::Alpha), red);
+        // 128/255 es casi la mitad: cada canal queda a medio camino, redondeado
+        assert_eq!(red.with_alpha(128).blend(BELOW, BlendMode::Alpha), Color::rgb(148, 50, 100));
+    }
+
+    #[test]
+    fn blending_is_always_opaque() {
+        let result = Color::new(10, 20, 30, 77).blend(BELOW.with_alpha(5), BlendMode::Additive);
+        assert!(result.is_opaque());
+    }
+
+    #[test]
+    fn additive_saturates() {
+        let light = Color::rgb(250, 200, 100);
+        assert_eq!(light.blend(BELOW, BlendMode::Additive), Color::rgb(255, 255, 255));
+        assert_eq!(Color::rgb(10, 20, 30).blend(BELOW, BlendMode::Additive), Color::rgb(50, 120, 230));
+        assert_eq!(light.with_alpha(0).blend(BELOW, BlendMode::Additive), BELOW);
+    }
+
+    #[test]
+    fn multiply() {
+        assert_eq!(Color::WHITE.blend(BELOW, BlendMode::Multiply), BELOW);
+        assert_eq!(Color::BLACK.blend(BELOW, BlendMode::Multiply), Color::BLACK);
+        assert_eq!(Color::rgb(128, 128, 128).blend(BELOW, BlendMode::Multiply), Color::rgb(20, 50, 100));
+        // A medio alfa, la sombra negra oscurece a la mitad
+        assert_eq!(Color::BLACK.with_alpha(128).blend(BELOW, BlendMode::Multiply), Color::rgb(20, 50, 100));
+    }
+
+    #[test]
+    fn mix_stays_inside_each_channel() {
+        let from = Color::from_hex(0x102030);
+        assert_eq!(from.mix(Color::WHITE, 0.0), from);
+        assert_eq!(from.mix(Color::WHITE, 1.0), Color::WHITE);
+        assert_eq!(Color::BLACK.mix(Color::from_hex(0x0000FF), 2.0).to_hex(), 0x0000FF);
+        assert_eq!(Color::from_hex(0x0000FF).mix(Color::BLACK, -1.0).to_hex(), 0x0000FF);
+        assert_eq!(from.with_alpha(7).mix(Color::WHITE, 0.5).a, 7);
+    }
+
+    #[test]
+    fn scale_saturates_in_white() {
+        assert_eq!(Color::rgb(100, 200, 40).scale(0.5), Color::rgb(50, 100, 20));
+        assert_eq!(Color::rgb(100, 200, 40).scale(2.0), Color::rgb(200, 255, 80));
+        assert_eq!(Color::new(100, 200, 40, 9).scale(0.0), Color::new(0, 0, 0, 9));
+    }
+
+    #[test]
+    fn u32_round_trip() {
+        for hex in [0x000000, 0xFFFFFF, 0x123456, 0xFF00FF, 0x0A0B0C] {
+            let color = Color::from(hex);
+            assert!(color.is_opaque());
+            assert_eq!(u32::from(color), hex);
+        }
+        assert_eq!(Color::from(0x123456), Color::rgb(0x12, 0x34, 0x56));
+        // El alfa no se guarda en 0xRRGGBB
+        assert_eq!(u32::from(Color::new(1, 2, 3, 4)), 0x010203);
+    }
+}
@@ -1,11 +1,34 @@
-use rusttype::{Font, Scale};
+use rusttype::Scale;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::color::{BlendMode, Color};
+use crate::text::{TextRenderer, TextStyle};
+use crate::texture::Texture;
+
+/// Rectángulo en píxeles: de una textura como origen de `blit` o del framebuffer como destino.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Rect { x, y, width, height }
+    }
+}
 
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
     background_color: u32,
-    current_color: u32,
+    current_color: Color,
+    blend_mode: BlendMode,
 }
 
 impl Framebuffer {
@@ -15,7 +38,8 @@ impl Framebuffer {
             height,
             buffer: vec![0; width * height],
             background_color: 0x000000,
-            current_color: 0xFFFFFF,
+            current_color: Color::WHITE,
+            blend_mode: BlendMode::default(),
         }
     }
 
@@ -27,45 +51,434 @@ impl Framebuffer {
 
     pub fn point(&mut self, x: usize, y: usize) {
         if x < self.width && y < self.height {
-            self.buffer[y * self.width + x] = self.current_color;
+            self.write(y * self.width + x, self.current_color);
+        }
+    }
+
+    /// Mezcla `color` en el píxel (x, y) con el modo actual, sin cambiar el color actual;
+    /// fuera de la pantalla no hace nada.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.write(y as usize * self.width + x as usize, color);
         }
     }
 
+    /// Escribe un color en el píxel `index` según el modo de mezcla actual.
+    fn write(&mut self, index: usize, color: Color) {
+        let pixel = &mut self.buffer[index];
+        *pixel = if self.blend_mode == BlendMode::Alpha && color.is_opaque() {
+            color.to_hex()
+        } else {
+            color.blend(Color::from_hex(*pixel), self.blend_mode).to_hex()
+        };
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
 
-    pub fn set_current_color(&mut self, color: u32) {
-        self.current_color = color;
+    /// Acepta 0xRRGGBB (opaco) o un `Color` con alfa.
+    pub fn set_current_color<C: Into<Color>>(&mut self, color: C) {
+        self.current_color = color.into();
     }
 
-    pub fn draw_text(&mut self, text: &str, x: usize, y: usize, scale: Scale, color: u32) {
-        let font_data = include_bytes!("assets/font.ttf") as &[u8];
-        let font = Font::try_from_bytes(font_data).unwrap();
-
-        let v_metrics = font.v_metrics(scale);
-
-        let offset = rusttype::point(x as f32, y as f32 + v_metrics.ascent);
-
-        let glyphs: Vec<_> = font.layout(text, scale, offset).collect();
-
-        for glyph in glyphs {
-            if let Some(bounding_box) = glyph.pixel_bounding_box() {
-                glyph.draw(|gx, gy, gv| {
-                    if gv > 0.5 {
-                        let gx = gx as i32 + bounding_box.min.x;
-                        let gy = gy as i32 + bounding_box.min.y;
-                
-                        if gx >= 0 && gx < self.width as i32 && gy >= 0 && gy < self.height as i32 {
-                            let gx = gx as usize;
-                            let gy = gy as usize;
-                
-                            self.buffer[gy * self.width + gx] = color;
-                        }
-                    }
-                });
-                
+    /// Modo con el que se combinan con lo ya dibujado todas las escrituras siguientes,
+    /// incluidas las de `blit`. Por defecto `BlendMode::Alpha`.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    /// Como `point` pero con coordenadas con signo; lo que cae fuera de la pantalla se descarta.
+    fn plot(&mut self, x: i32, y: i32) {
+        if x >= 0 && y >= 0 {
+            self.point(x as usize, y as usize);
+        }
+    }
+
+    /// Línea de Bresenham del color actual, con los dos extremos incluidos. Se recorre el eje
+    /// largo solo en el tramo que cae en pantalla y el otro se redondea sobre la recta ideal
+    /// (las mitades hacia el final), así los extremos pueden estar tan lejos como quepa en un i32.
+    pub fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        let (dx, dy) = (x1 as i64 - x0 as i64, y1 as i64 - y0 as i64);
+        if dx.abs() >= dy.abs() {
+            self.line_along(x0 as i64, y0 as i64, dx, dy, self.width as i64, |x, y| (x, y));
+        } else {
+            self.line_along(y0 as i64, x0 as i64, dy, dx, self.height as i64, |y, x| (x, y));
+        }
+    }
+
+    /// Recorre `a` de `a0` a `a0 + da` pasando de largo lo que queda fuera de `0..extent`;
+    /// `b` avanza `db` en total. `to_xy` pasa de (a, b) a (x, y).
+    fn line_along(&mut self, a0: i64, b0: i64, da: i64, db: i64, extent: i64, to_xy: fn(i64, i64) -> (i64, i64)) {
+        let (steps, step_a, step_b) = (da.abs(), da.signum(), db.signum());
+        // Pasos k con a0 + k * step_a dentro de 0..extent
+        let (first, last) = match step_a {
+            1 => (-a0, extent - 1 - a0),
+            -1 => (a0 - (extent - 1), a0),
+            _ => (0, 0),
+        };
+        let (first, last) = (first.max(0), last.min(steps));
+
+        for k in first..=last {
+            let offset = if steps == 0 {
+                0
+            } else {
+                ((2 * k as i128 * db.abs() as i128 + steps as i128) / (2 * steps as i128)) as i64
+            };
+            let (x, y) = to_xy(a0 + k * step_a, b0 + offset * step_b);
+            if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+                self.point(x as usize, y as usize);
+            }
+        }
+    }
+
+    /// Contorno de un rectángulo de `width` x `height` píxeles con la esquina en (x, y).
+    pub fn rect(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let (right, bottom) = (x.saturating_add(width - 1), y.saturating_add(height - 1));
+        self.line(x, y, right, y);
+        self.line(x, bottom, right, bottom);
+        self.line(x, y, x, bottom);
+        self.line(right, y, right, bottom);
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        let (x0, x1) = (x.max(0), x.saturating_add(width).min(self.width as i32));
+        let (y0, y1) = (y.max(0), y.saturating_add(height).min(self.height as i32));
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        for y in y0 as usize..y1 as usize {
+            let row = y * self.width;
+            if self.blend_mode == BlendMode::Alpha && self.current_color.is_opaque() {
+                self.buffer[row + x0 as usize..row + x1 as usize].fill(self.current_color.to_hex());
+            } else {
+                for index in row + x0 as usize..row + x1 as usize {
+                    self.write(index, self.current_color);
+                }
+            }
+        }
+    }
+
+    /// Circunferencia por el algoritmo del punto medio.
+    pub fn circle(&mut self, cx: i32, cy: i32, radius: i32) {
+        if radius < 0 {
+            return;
+        }
+
+        let (mut x, mut y) = (radius, 0);
+        let mut error = 1 - radius;
+        while x >= y {
+            for (dx, dy) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                self.plot(cx + dx, cy + dy);
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Círculo relleno, una fila horizontal por cada altura para no pintar dos veces un píxel.
+    /// Solo se recorren las filas que caen en pantalla; las cuentas van en i64 porque el cuadrado
+    /// del radio no cabe en un i32 a partir de 46341.
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: i32) {
+        let (cx, cy, radius) = (cx as i64, cy as i64, radius as i64);
+        let (top, bottom) = ((cy - radius).max(0), (cy + radius).min(self.height as i64 - 1));
+
+        for y in top..=bottom {
+            let dy = y - cy;
+            let half = ((radius * radius - dy * dy) as f64).sqrt().round() as i64;
+            let (left, right) = ((cx - half).max(0), (cx + half).min(self.width as i64 - 1));
+            if left <= right {
+                self.fill_rect(left as i32, y as i32, (right - left + 1) as i32, 1);
             }
         }
     }
+
+    /// Contorno cerrado que une los puntos en orden y el último con el primero.
+    pub fn polygon(&mut self, points: &[(i32, i32)]) {
+        for (index, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(index + 1) % points.len()];
+            self.line(x0, y0, x1, y1);
+        }
+    }
+
+    /// Copia la región `src` de la textura estirada o encogida a `dst` (vecino más cercano).
+    /// Cada texel se mezcla con su propio alfa, así los bordes suaves de los sprites se conservan.
+    pub fn blit(&mut self, texture: &Texture, src: Rect, dst: Rect) {
+        self.blit_with(texture, src, dst, Some);
+    }
+
+    /// Como `blit`, pero cada color pasa por `shade`, que lo cambia (luz, niebla...) o
+    /// devuelve `None` para no dibujar ese texel.
+    pub fn blit_with<F: FnMut(Color) -> Option<Color>>(&mut self, texture: &Texture, src: Rect, dst: Rect, mut shade: F) {
+        if src.width <= 0 || src.height <= 0 || dst.width <= 0 || dst.height <= 0 {
+            return;
+        }
+
+        // En i64: ni el borde derecho ni el producto por el tamaño de `src` caben siempre en un i32
+        let (src_x, src_y, src_width, src_height) = (src.x as i64, src.y as i64, src.width as i64, src.height as i64);
+        let (dst_x, dst_y, dst_width, dst_height) = (dst.x as i64, dst.y as i64, dst.width as i64, dst.height as i64);
+        let (x0, x1) = (dst_x.max(0), (dst_x + dst_width).min(self.width as i64));
+        let (y0, y1) = (dst_y.max(0), (dst_y + dst_height).min(self.height as i64));
+        for y in y0..y1 {
+            let ty = src_y + (y - dst_y) * src_height / dst_height;
+            for x in x0..x1 {
+                let tx = src_x + (x - dst_x) * src_width / dst_width;
+                if tx < 0 || ty < 0 || tx >= texture.width as i64 || ty >= texture.height as i64 {
+                    continue;
+                }
+                let texel = texture.get_pixel(tx as u32, ty as u32);
+                if texel.a == 0 {
+                    continue;
+                }
+                if let Some(color) = shade(texel) {
+                    self.write(y as usize * self.width + x as usize, color);
+                }
+            }
+        }
+    }
+
+    /// Copia del contenido como imagen RGB del crate `image`.
+    pub fn to_image(&self) -> image::RgbImage {
+        image::RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let color = self.buffer[y as usize * self.width + x as usize];
+            image::Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+        })
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> image::ImageResult<()> {
+        self.to_image().save_with_format(path, image::ImageFormat::Png)
+    }
+
+    /// PPM binario (P6): sin compresión ni dependencias, lo abre casi cualquier visor.
+    pub fn save_ppm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        write!(file, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for &color in &self.buffer {
+            file.write_all(&[(color >> 16) as u8, (color >> 8) as u8, color as u8])?;
+        }
+        file.flush()
+    }
+
+    /// Una línea de texto con la parte de arriba en (x, y), con la fuente por defecto.
+    /// Para alinear, partir en líneas o usar otras fuentes está `TextRenderer`.
+    pub fn draw_text(&mut self, text: &str, x: usize, y: usize, scale: Scale, color: u32) {
+        TextRenderer::shared().draw(self, text, x as i32, y as i32, &TextStyle::new(scale.y).color(color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Carpeta temporal propia de cada prueba, vacía al empezar.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("maze-framebuffer-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 3x2 con un color distinto en cada píxel, para notar filas o canales cambiados.
+    fn sample() -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        let colors = [0xFF0000, 0x00FF00, 0x0000FF, 0x123456, 0xFFFFFF, 0x000000];
+        for (index, &color) in colors.iter().enumerate() {
+            framebuffer.set_current_color(color);
+            framebuffer.point(index % 3, index / 3);
+        }
+        framebuffer
+    }
+
+    fn assert_same_pixels(framebuffer: &Framebuffer, image: &image::RgbImage) {
+        assert_eq!(image.dimensions(), (framebuffer.width as u32, framebuffer.height as u32));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = framebuffer.buffer[y as usize * framebuffer.width + x as usize];
+            assert_eq!(Color::rgb(pixel[0], pixel[1], pixel[2]).to_hex(), expected, "({}, {})", x, y);
+        }
+    }
+
+    /// Píxeles distintos del fondo, como (x, y).
+    fn painted(framebuffer: &Framebuffer) -> Vec<(usize, usize)> {
+        (0..framebuffer.buffer.len())
+            .filter(|&index| framebuffer.buffer[index] != framebuffer.background_color)
+            .map(|index| (index % framebuffer.width, index / framebuffer.width))
+            .collect()
+    }
+
+    fn canvas() -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(5, 3);
+        framebuffer.set_current_color(0xFFFFFF);
+        framebuffer
+    }
+
+    #[test]
+    fn lines_are_clipped() {
+        let mut framebuffer = canvas();
+        framebuffer.line(-20, -4, 30, -4);
+        framebuffer.line(-3, -3, -3, 10);
+        framebuffer.line(8, 0, 12, 2);
+        assert!(painted(&framebuffer).is_empty());
+
+        // Cruza la pantalla de lado a lado: se ve la fila entera
+        framebuffer.line(-10, 1, 20, 1);
+        assert_eq!(painted(&framebuffer), (0..5).map(|x| (x, 1)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn circles_are_clipped() {
+        let mut framebuffer = canvas();
+        framebuffer.circle(-100, -100, 10);
+        framebuffer.fill_circle(-100, 50, 10);
+        framebuffer.circle(2, 1, 40);
+        assert!(painted(&framebuffer).is_empty());
+
+        // Centro fuera de la pantalla: solo asoma el borde derecho, en la columna 0
+        framebuffer.circle(-3, 1, 3);
+        assert_eq!(painted(&framebuffer), vec![(0, 0), (0, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn huge_filled_circles_cover_the_screen() {
+        for radius in [46341, 1_000_000, i32::MAX] {
+            let mut framebuffer = canvas();
+            framebuffer.fill_circle(2, 1, radius);
+            assert_eq!(painted(&framebuffer).len(), 15, "radius {}", radius);
+        }
+    }
+
+    #[test]
+    fn lines_with_far_endpoints() {
+        // Antes se desbordaba al restar los extremos
+        let mut framebuffer = canvas();
+        framebuffer.line(i32::MIN, 0, i32::MAX, 0);
+        assert_eq!(painted(&framebuffer), (0..5).map(|x| (x, 0)).collect::<Vec<_>>());
+
+        // Antes recorría los 200 millones de píxeles; ahora solo los de la pantalla
+        let start = std::time::Instant::now();
+        let mut framebuffer = canvas();
+        framebuffer.line(0, 2, 200_000_000, 2);
+        framebuffer.line(4, -200_000_000, 4, 200_000_000);
+        assert!(start.elapsed() < std::time::Duration::from_millis(100));
+        assert_eq!(painted(&framebuffer).len(), 7);
+
+        // La diagonal entre las dos esquinas extremas pasa por (0, 0)
+        let mut framebuffer = canvas();
+        framebuffer.line(i32::MIN, i32::MIN, i32::MAX, i32::MAX);
+        assert_eq!(painted(&framebuffer), vec![(0, 0), (1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn rects_near_the_i32_limits() {
+        let mut framebuffer = canvas();
+        framebuffer.fill_rect(i32::MAX - 5, 0, 10, 1);
+        framebuffer.rect(i32::MAX - 5, 0, 10, 1);
+        framebuffer.fill_rect(i32::MIN, i32::MIN, i32::MAX, i32::MAX);
+        assert!(painted(&framebuffer).is_empty());
+
+        framebuffer.fill_rect(-5, 1, i32::MAX, 1);
+        assert_eq!(painted(&framebuffer), (0..5).map(|x| (x, 1)).collect::<Vec<_>>());
+
+        // Contorno que empieza en pantalla y acaba más allá de i32::MAX: se ven sus dos lados
+        let mut framebuffer = canvas();
+        framebuffer.rect(1, 1, i32::MAX, i32::MAX);
+        assert_eq!(painted(&framebuffer), vec![(1, 1), (2, 1), (3, 1), (4, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn polygons_are_clipped() {
+        let mut framebuffer = canvas();
+        // Un rectángulo que rodea la pantalla sin tocarla
+        framebuffer.polygon(&[(-1, -1), (5, -1), (5, 3), (-1, 3)]);
+        assert!(painted(&framebuffer).is_empty());
+
+        // Un triángulo con dos vértices fuera: solo se ve su base
+        framebuffer.polygon(&[(-3, 2), (7, 2), (2, -8)]);
+        assert!(painted(&framebuffer).iter().all(|&(_, y)| y <= 2));
+        assert!((0..5).all(|x| painted(&framebuffer).contains(&(x, 2))));
+    }
+
+    #[test]
+    fn blits_with_negative_origin() {
+        // Textura de 2x2: rojo, verde / azul, blanco
+        let dir = scratch_dir("blit");
+        let path = dir.join("texture.png");
+        image::RgbaImage::from_fn(2, 2, |x, y| match (x, y) {
+            (0, 0) => image::Rgba([255, 0, 0, 255]),
+            (1, 0) => image::Rgba([0, 255, 0, 255]),
+            (0, 1) => image::Rgba([0, 0, 255, 255]),
+            _ => image::Rgba([255, 255, 255, 255]),
+        })
+        .save(&path)
+        .unwrap();
+        let texture = Texture::new(path.to_str().unwrap()).unwrap();
+        let full = Rect::new(0, 0, 2, 2);
+
+        // Solo el texel de abajo a la derecha cae en la pantalla
+        let mut framebuffer = canvas();
+        framebuffer.blit(&texture, full, Rect::new(-1, -1, 2, 2));
+        assert_eq!(painted(&framebuffer), vec![(0, 0)]);
+        assert_eq!(framebuffer.buffer[0], 0xFFFFFF);
+
+        // Ampliada al doble: la columna derecha de la textura ocupa las dos primeras de la pantalla
+        let mut framebuffer = canvas();
+        framebuffer.blit(&texture, full, Rect::new(-2, 0, 4, 4));
+        assert_eq!(framebuffer.buffer[..2], [0x00FF00, 0x00FF00]);
+        assert_eq!(framebuffer.buffer[10..12], [0xFFFFFF, 0xFFFFFF]);
+        assert_eq!(painted(&framebuffer).len(), 6);
+
+        // Completamente fuera
+        let mut framebuffer = canvas();
+        framebuffer.blit(&texture, full, Rect::new(-10, -10, 4, 4));
+        assert!(painted(&framebuffer).is_empty());
+
+        // Destinos que acaban más allá de i32::MAX o empiezan muy a la izquierda
+        let mut framebuffer = canvas();
+        framebuffer.blit(&texture, full, Rect::new(i32::MAX - 5, 0, 10, 10));
+        assert!(painted(&framebuffer).is_empty());
+        framebuffer.blit(&texture, full, Rect::new(i32::MIN, i32::MIN, i32::MAX, i32::MAX));
+        assert!(painted(&framebuffer).is_empty());
+        framebuffer.blit(&texture, full, Rect::new(-i32::MAX, 0, i32::MAX, 2));
+        assert!(painted(&framebuffer).is_empty());
+        framebuffer.blit(&texture, full, Rect::new(0, 0, i32::MAX, i32::MAX));
+        assert_eq!(painted(&framebuffer).len(), 15);
+        assert_eq!(framebuffer.buffer[14], 0xFF0000);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let framebuffer = sample();
+        let path = scratch_dir("png").join("frame.png");
+        framebuffer.save_png(&path).unwrap();
+
+        assert_same_pixels(&framebuffer, &image::open(&path).unwrap().to_rgb8());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let framebuffer = sample();
+        let path = scratch_dir("ppm").join("frame.ppm");
+        framebuffer.save_ppm(&path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        let header = b"P6\n3 2\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(bytes.len(), header.len() + 3 * 2 * 3);
+
+        assert_same_pixels(&framebuffer, &image::open(&path).unwrap().to_rgb8());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
 }
@@ -0,0 +1,285 @@
+use minifb::Window;
+use nalgebra_glm::Vec2;
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+use crate::audio::AudioPlayer;
+use crate::campaign::Campaign;
+use crate::framebuffer::Framebuffer;
+use crate::lighting::LightMap;
+use crate::maze::{Level, MazeError};
+use crate::player::Player;
+use crate::texture::LevelTextures;
+
+mod defeat;
+mod level_select;
+mod menu;
+mod paused;
+mod playing;
+mod settings;
+mod victory;
+
+/// Pantallas del juego. Cada una tiene su módulo con `input`, `update` y `render`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    Menu,
+    Playing,
+    Paused,
+    Victory,
+    Defeat,
+    Settings,
+    LevelSelect,
+}
+
+/// Lo que pide un manejador: seguir en la pantalla actual, cambiar a otra o cerrar el juego.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transition {
+    Stay,
+    Switch(GameState),
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewMode {
+    TwoD,
+    ThreeD,
+}
+
+pub struct Settings {
+    pub mode: ViewMode,
+    pub show_fps: bool,
+    pub music_volume: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: ViewMode::ThreeD,
+            show_fps: true,
+            music_volume: 1.0,
+        }
+    }
+}
+
+pub struct Game {
+    pub state: GameState,
+    pub level: Level,
+    pub campaign: Option<Campaign>,
+    pub textures: LevelTextures,
+    pub lights: LightMap,
+    pub player: Player,
+    pub settings: Settings,
+    pub show_hint: bool,
+    pub level_time: Duration,
+    // Copia del nivel tal como se cargó, para reiniciarlo
+    pristine: Level,
+    level_start: Instant,
+    // Posición previa del mouse; se olvida al salir del juego para no girar de golpe al volver
+    last_mouse_x: Option<f32>,
+    // Opción resaltada en las pantallas con listas (ajustes, selección de nivel)
+    cursor: usize,
+    title_changed: bool,
+    music: Option<AudioPlayer>,
+    // Sin él la partida es muda: tampoco suena la música
+    walking_player: Option<AudioPlayer>,
+}
+
+pub fn has_won(player: &Player, level: &Level) -> bool {
+    let block_size = level.block_size;
+    let goal_position = &level.goal;
+    let player_block_x = (player.pos.x / block_size as f32).round() as usize;
+    let player_block_y = (player.pos.y / block_size as f32).round() as usize;
+    let goal_block_x = (goal_position.x / block_size as f32).round() as usize;
+    let goal_block_y = (goal_position.y / block_size as f32).round() as usize;
+
+    player_block_x == goal_block_x && player_block_y == goal_block_y
+}
+
+/// El jugador pierde si un enemigo lo alcanza.
+pub fn has_lost(player: &Player, level: &Level) -> bool {
+    let reach = level.block_size as f32 * 0.3;
+    level.enemies.iter().any(|enemy| nalgebra_glm::distance(enemy, &player.pos) < reach)
+}
+
+pub fn spawn_player(level: &Level) -> Player {
+    Player {
+        pos: level.spawn,
+        a: level.spawn_angle,
+        fov: PI / 3.0,
+        velocity: Vec2::new(0.0, 0.0), // Inicializando velocity
+        previous_mouse_pos: Vec2::new(0.0, 0.0), // Inicializando previous_mouse_pos
+        keys: 0,
+    }
+}
+
+fn start_music(level: &Level, volume: f32, silent: bool) -> Result<Option<AudioPlayer>, MazeError> {
+    let Some(music) = level.config.music.as_ref().filter(|_| !silent) else {
+        return Ok(None);
+    };
+
+    let audio_player = AudioPlayer::new(music)
+        .map_err(|err| MazeError::Asset { path: music.clone(), message: err.to_string() })?;
+    audio_player.set_volume(volume);
+    audio_player.play();
+    Ok(Some(audio_player))
+}
+
+impl Game {
+    /// Con `walking_player` a `None` el juego no abre ningún dispositivo de audio, para
+    /// usarlo sin ventana (capturas, pruebas). Falla si falta alguna textura o la música del nivel.
+    pub fn new(level: Level, campaign: Option<Campaign>, walking_player: Option<AudioPlayer>) -> Result<Self, MazeError> {
+        let settings = Settings::default();
+
+        Ok(Game {
+            state: GameState::Menu,
+            textures: LevelTextures::load(&level.config)?,
+            lights: LightMap::new(&level),
+            player: spawn_player(&level),
+            music: start_music(&level, settings.music_volume, walking_player.is_none())?,
+            pristine: level.clone(),
+            level,
+            campaign,
+            settings,
+            show_hint: false,
+            level_time: Duration::ZERO,
+            level_start: Instant::now(),
+            last_mouse_x: None,
+            cursor: 0,
+            title_changed: true,
+            walking_player,
+        })
+    }
+
+    pub fn title(&self) -> String {
+        match &self.campaign {
+            Some(campaign) => format!("{} - {}", campaign.name, self.level.config.name),
+            None => self.level.config.name.clone(),
+        }
+    }
+
+    /// Sustituye el nivel en juego: texturas, música y jugador se reconstruyen. Si algún recurso
+    /// no se puede cargar, el nivel actual sigue como estaba.
+    pub fn start_level(&mut self, level: Level) -> Result<(), MazeError> {
+        let textures = LevelTextures::load(&level.config)?;
+        let music = start_music(&level, self.settings.music_volume, self.walking_player.is_none())?;
+        self.music = music;
+        self.textures = textures;
+        self.lights = LightMap::new(&level);
+        self.pristine = level.clone();
+        self.level = level;
+        self.title_changed = true;
+        self.restart_level();
+        Ok(())
+    }
+
+    pub fn restart_level(&mut self) {
+        self.level = self.pristine.clone();
+        self.player = spawn_player(&self.level);
+        self.show_hint = false;
+        self.level_time = Duration::ZERO;
+    }
+
+    /// Carga el nivel `index` de la campaña; los errores se informan y el nivel actual se conserva.
+    pub fn start_campaign_level(&mut self, index: usize) -> bool {
+        let Some(campaign) = self.campaign.as_ref() else {
+            return false;
+        };
+
+        let path = campaign.level_path(index);
+        match campaign.load_level(index).and_then(|level| self.start_level(level)) {
+            Ok(()) => {
+                if let Some(campaign) = self.campaign.as_mut() {
+                    campaign.current = index;
+                }
+                true
+            }
+            Err(err) => {
+                eprintln!("Error al cargar {}: {}", path, err);
+                false
+            }
+        }
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.settings.music_volume = volume.clamp(0.0, 1.0);
+        if let Some(music) = &self.music {
+            music.set_volume(self.settings.music_volume);
+        }
+    }
+
+    /// Un frame completo del estado actual. Devuelve `false` cuando el juego debe cerrarse.
+    pub fn frame(&mut self, window: &mut Window, framebuffer: &mut Framebuffer) -> bool {
+        let transition = match self.input(window) {
+            Transition::Stay => self.update(),
+            transition => transition,
+        };
+
+        self.render(framebuffer);
+
+        if self.title_changed {
+            window.set_title(&self.title());
+            self.title_changed = false;
+        }
+
+        match transition {
+            Transition::Stay => true,
+            Transition::Switch(next) => {
+                self.enter(next);
+                true
+            }
+            Transition::Quit => false,
+        }
+    }
+
+    fn input(&mut self, window: &Window) -> Transition {
+        match self.state {
+            GameState::Menu => menu::input(self, window),
+            GameState::Playing => playing::input(self, window),
+            GameState::Paused => paused::input(self, window),
+            GameState::Victory => victory::input(self, window),
+            GameState::Defeat => defeat::input(self, window),
+            GameState::Settings => settings::input(self, window),
+            GameState::LevelSelect => level_select::input(self, window),
+        }
+    }
+
+    /// Avanza la lógica de la pantalla actual sin leer la ventana; sirve para bots y pruebas.
+    pub fn update(&mut self) -> Transition {
+        match self.state {
+            GameState::Playing => playing::update(self),
+            _ => Transition::Stay,
+        }
+    }
+
+    /// Dibuja la pantalla actual en `framebuffer`, que no tiene por qué ser el de una ventana.
+    pub fn render(&self, framebuffer: &mut Framebuffer) {
+        match self.state {
+            GameState::Menu => menu::render(self, framebuffer),
+            GameState::Playing => playing::render(self, framebuffer),
+            GameState::Paused => paused::render(self, framebuffer),
+            GameState::Victory => victory::render(self, framebuffer),
+            GameState::Defeat => defeat::render(self, framebuffer),
+            GameState::Settings => settings::render(self, framebuffer),
+            GameState::LevelSelect => level_select::render(self, framebuffer),
+        }
+    }
+
+    /// Cambia de pantalla, parando o reanudando el cronómetro del nivel.
+    pub fn enter(&mut self, next: GameState) {
+        // El cronómetro del nivel solo corre mientras se juega
+        if self.state == GameState::Playing {
+            self.level_time = self.level_start.elapsed();
+            if let Some(walking_player) = &self.walking_player {
+                walking_player.pause();
+            }
+        }
+        if next == GameState::Playing {
+            self.level_start = Instant::now() - self.level_time;
+            self.last_mouse_x = None;
+        }
+        if matches!(next, GameState::Settings | GameState::LevelSelect) {
+            self.cursor = 0;
+        }
+        self.state = next;
+    }
+}
@@ -0,0 +1,23 @@
+use minifb::{Key, KeyRepeat, Window};
+use rusttype::Scale;
+
+use super::{Game, GameState, Transition};
+use crate::framebuffer::Framebuffer;
+
+pub fn input(game: &mut Game, window: &Window) -> Transition {
+    if window.is_key_pressed(Key::R, KeyRepeat::No) || window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+        game.restart_level();
+        return Transition::Switch(GameState::Playing);
+    }
+    if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+        return Transition::Switch(GameState::Menu);
+    }
+    Transition::Stay
+}
+
+pub fn render(game: &Game, framebuffer: &mut Framebuffer) {
+    framebuffer.draw_text("¡Te atraparon!", 100, framebuffer.height / 2, Scale::uniform(48.0), 0xFF4040);
+    let time_text = format!("Tiempo: {:.1} s", game.level_time.as_secs_f32());
+    framebuffer.draw_text(&time_text, 100, framebuffer.height / 2 + 60, Scale::uniform(32.0), 0xFF4040);
+    framebuffer.draw_text("R: reintentar   Esc: volver al menú", 100, framebuffer.height / 2 + 110, Scale::uniform(32.0), 0xFF4040);
+}
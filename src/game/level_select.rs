@@ -0,0 +1,52 @@
+use minifb::{Key, KeyRepeat, Window};
+use rusttype::Scale;
+
+use super::{Game, GameState, Transition};
+use crate::framebuffer::Framebuffer;
+
+pub fn input(game: &mut Game, window: &Window) -> Transition {
+    let Some(count) = game.campaign.as_ref().map(|campaign| campaign.level_count()) else {
+        return Transition::Switch(GameState::Menu);
+    };
+
+    if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+        return Transition::Switch(GameState::Menu);
+    }
+    if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+        game.cursor = (game.cursor + count - 1) % count;
+    }
+    if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+        game.cursor = (game.cursor + 1) % count;
+    }
+
+    let unlocked = game.campaign.as_ref().is_some_and(|campaign| campaign.is_unlocked(game.cursor));
+    if window.is_key_pressed(Key::Enter, KeyRepeat::No) && unlocked && game.start_campaign_level(game.cursor) {
+        return Transition::Switch(GameState::Playing);
+    }
+
+    Transition::Stay
+}
+
+pub fn render(game: &Game, framebuffer: &mut Framebuffer) {
+    let Some(campaign) = &game.campaign else {
+        return;
+    };
+
+    framebuffer.draw_text(&campaign.name, 400, 200, Scale::uniform(48.0), 0xFFFFFF);
+
+    for index in 0..campaign.level_count() {
+        let label = if campaign.is_unlocked(index) {
+            format!("{}. {}", index + 1, campaign.level_path(index))
+        } else {
+            format!("{}. (bloqueado)", index + 1)
+        };
+        let color = match (index == game.cursor, campaign.is_unlocked(index)) {
+            (true, _) => 0xFFFF00,
+            (false, true) => 0xFFFFFF,
+            (false, false) => 0x777777,
+        };
+        framebuffer.draw_text(&label, 400, 280 + index * 45, Scale::uniform(32.0), color);
+    }
+
+    framebuffer.draw_text("Flechas: elegir   Enter: jugar   Esc: volver", 400, 300 + campaign.level_count() * 45, Scale::uniform(24.0), 0xAAAAAA);
+}
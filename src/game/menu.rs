@@ -0,0 +1,34 @@
+use minifb::{Key, KeyRepeat, Window};
+use rusttype::Scale;
+
+use super::{Game, GameState, Transition};
+use crate::framebuffer::Framebuffer;
+
+pub fn input(game: &mut Game, window: &Window) -> Transition {
+    if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+        game.restart_level();
+        return Transition::Switch(GameState::Playing);
+    }
+    if window.is_key_pressed(Key::L, KeyRepeat::No) && game.campaign.is_some() {
+        return Transition::Switch(GameState::LevelSelect);
+    }
+    if window.is_key_pressed(Key::S, KeyRepeat::No) {
+        return Transition::Switch(GameState::Settings);
+    }
+    if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+        return Transition::Quit;
+    }
+    Transition::Stay
+}
+
+pub fn render(game: &Game, framebuffer: &mut Framebuffer) {
+    framebuffer.draw_text("Presiona ENTER para comenzar", 400, 450, Scale::uniform(32.0), 0xFFFFFF);
+
+    let mut y = 510;
+    if game.campaign.is_some() {
+        framebuffer.draw_text("L: elegir nivel", 400, y, Scale::uniform(24.0), 0xAAAAAA);
+        y += 35;
+    }
+    framebuffer.draw_text("S: ajustes", 400, y, Scale::uniform(24.0), 0xAAAAAA);
+    framebuffer.draw_text("Esc: salir", 400, y + 35, Scale::uniform(24.0), 0xAAAAAA);
+}
@@ -0,0 +1,30 @@
+use minifb::{Key, KeyRepeat, Window};
+
+use super::{Game, GameState, Transition, playing};
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::text::{Align, TextRenderer, TextStyle};
+
+pub fn input(_game: &mut Game, window: &Window) -> Transition {
+    if window.is_key_pressed(Key::Escape, KeyRepeat::No) || window.is_key_pressed(Key::P, KeyRepeat::No) {
+        return Transition::Switch(GameState::Playing);
+    }
+    if window.is_key_pressed(Key::Q, KeyRepeat::No) {
+        return Transition::Switch(GameState::Menu);
+    }
+    Transition::Stay
+}
+
+pub fn render(game: &Game, framebuffer: &mut Framebuffer) {
+    // La escena sigue visible detrás del texto
+    playing::render(game, framebuffer);
+
+    // Velo oscuro translúcido para que el texto se lea sobre cualquier escena
+    framebuffer.set_current_color(Color::new(0, 0, 0, 160));
+    framebuffer.fill_rect(0, 0, framebuffer.width as i32, framebuffer.height as i32);
+
+    let text = TextRenderer::shared();
+    let center = framebuffer.width as i32 / 2;
+    text.draw(framebuffer, "PAUSA", center, 400, &TextStyle::new(64.0).align(Align::Center));
+    text.draw(framebuffer, "P o Esc: continuar   Q: volver al menú", center, 480, &TextStyle::new(32.0).align(Align::Center));
+}
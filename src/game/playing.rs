@@ -0,0 +1,94 @@
+use minifb::{Key, KeyRepeat, MouseMode, Window};
+
+use super::{Game, GameState, Transition, ViewMode, has_lost, has_won};
+use crate::framebuffer::Framebuffer;
+use crate::maze::{solve, ItemKind};
+use crate::player::{process_events, use_door};
+use crate::render::{render2d, render3d, render_enemies, render_minimap};
+
+pub fn input(game: &mut Game, window: &Window) -> Transition {
+    if window.is_key_pressed(Key::Escape, KeyRepeat::No) || window.is_key_pressed(Key::P, KeyRepeat::No) {
+        return Transition::Switch(GameState::Paused);
+    }
+    if window.is_key_pressed(Key::M, KeyRepeat::No) {
+        game.settings.mode = match game.settings.mode {
+            ViewMode::TwoD => ViewMode::ThreeD,
+            ViewMode::ThreeD => ViewMode::TwoD,
+        };
+    }
+    // H muestra u oculta el camino hasta la salida
+    if window.is_key_pressed(Key::H, KeyRepeat::No) {
+        game.show_hint = !game.show_hint;
+    }
+    // E abre y cierra puertas
+    if window.is_key_pressed(Key::E, KeyRepeat::No) {
+        use_door(&mut game.player, &mut game.level);
+    }
+
+    // Captura del movimiento del mouse
+    if let Some((mouse_x, _)) = window.get_mouse_pos(MouseMode::Clamp) {
+        let mouse_delta_x = mouse_x - game.last_mouse_x.unwrap_or(mouse_x);
+        game.player.a += mouse_delta_x * 0.005; // Cambiado a suma para invertir la rotación
+        game.last_mouse_x = Some(mouse_x);
+    }
+
+    // Procesar eventos
+    process_events(window, &mut game.player, &game.level, game.walking_player.as_ref());
+
+    Transition::Stay
+}
+
+pub fn update(game: &mut Game) -> Transition {
+    for door in game.level.doors.values_mut() {
+        door.update();
+    }
+    game.lights.sync_doors(&game.level);
+    game.lights.update(game.level_start.elapsed().as_secs_f32());
+
+    // Las llaves se recogen al pasar por encima
+    let reach = game.level.block_size as f32 * 0.4;
+    let player = &mut game.player;
+    game.level.items.retain(|item| {
+        let picked = item.kind == ItemKind::Key && nalgebra_glm::distance(&item.pos, &player.pos) < reach;
+        if picked {
+            player.keys += 1;
+        }
+        !picked
+    });
+
+    // Verificar condición de victoria
+    if has_won(&game.player, &game.level) {
+        if let Some(campaign) = &mut game.campaign {
+            if let Err(err) = campaign.complete_current() {
+                eprintln!("No se pudo guardar el progreso: {}", err);
+            }
+        }
+        return Transition::Switch(GameState::Victory);
+    }
+
+    if has_lost(&game.player, &game.level) {
+        return Transition::Switch(GameState::Defeat);
+    }
+
+    Transition::Stay
+}
+
+pub fn render(game: &Game, framebuffer: &mut Framebuffer) {
+    let hint = if game.show_hint {
+        solve::bfs(&game.level.maze, game.level.cell_at(game.player.pos), game.level.goal_cell())
+    } else {
+        None
+    };
+
+    match game.settings.mode {
+        ViewMode::TwoD => render2d(framebuffer, &game.level, &game.player, hint.as_deref()),
+        ViewMode::ThreeD => {
+            let mut z_buffer = vec![f32::INFINITY; framebuffer.width];
+            render3d(framebuffer, &game.level, &game.textures, &game.lights, &game.player, &mut z_buffer);
+            render_enemies(framebuffer, &game.level, &game.textures, &game.lights, &game.player, &mut z_buffer);
+        }
+    }
+
+    // Renderizar el minimapa
+    render_minimap(framebuffer, &game.level, &game.textures, &game.player, hint.as_deref());
+}
@@ -0,0 +1,56 @@
+use minifb::{Key, KeyRepeat, Window};
+use rusttype::Scale;
+
+use super::{Game, GameState, Transition, ViewMode};
+use crate::framebuffer::Framebuffer;
+
+const OPTIONS: usize = 3;
+
+pub fn input(game: &mut Game, window: &Window) -> Transition {
+    if window.is_key_pressed(Key::Escape, KeyRepeat::No) || window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+        return Transition::Switch(GameState::Menu);
+    }
+    if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+        game.cursor = (game.cursor + OPTIONS - 1) % OPTIONS;
+    }
+    if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+        game.cursor = (game.cursor + 1) % OPTIONS;
+    }
+
+    let step = if window.is_key_pressed(Key::Right, KeyRepeat::Yes) {
+        1.0
+    } else if window.is_key_pressed(Key::Left, KeyRepeat::Yes) {
+        -1.0
+    } else {
+        return Transition::Stay;
+    };
+
+    match game.cursor {
+        0 => {
+            game.settings.mode = match game.settings.mode {
+                ViewMode::TwoD => ViewMode::ThreeD,
+                ViewMode::ThreeD => ViewMode::TwoD,
+            }
+        }
+        1 => game.settings.show_fps = !game.settings.show_fps,
+        _ => game.set_music_volume(game.settings.music_volume + step * 0.1),
+    }
+
+    Transition::Stay
+}
+
+pub fn render(game: &Game, framebuffer: &mut Framebuffer) {
+    framebuffer.draw_text("Ajustes", 400, 300, Scale::uniform(48.0), 0xFFFFFF);
+
+    let options = [
+        format!("Vista: {}", if game.settings.mode == ViewMode::ThreeD { "3D" } else { "2D" }),
+        format!("Mostrar FPS: {}", if game.settings.show_fps { "sí" } else { "no" }),
+        format!("Volumen de la música: {:.0}%", game.settings.music_volume * 100.0),
+    ];
+    for (index, option) in options.iter().enumerate() {
+        let color = if index == game.cursor { 0xFFFF00 } else { 0xFFFFFF };
+        framebuffer.draw_text(option, 400, 380 + index * 50, Scale::uniform(32.0), color);
+    }
+
+    framebuffer.draw_text("Flechas: cambiar   Enter o Esc: volver", 400, 560, Scale::uniform(24.0), 0xAAAAAA);
+}
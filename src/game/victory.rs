@@ -0,0 +1,45 @@
+use minifb::{Key, KeyRepeat, Window};
+use rusttype::Scale;
+
+use super::{Game, GameState, Transition};
+use crate::framebuffer::Framebuffer;
+
+fn has_next(game: &Game) -> bool {
+    game.campaign.as_ref().is_some_and(|campaign| campaign.has_next())
+}
+
+pub fn input(game: &mut Game, window: &Window) -> Transition {
+    // Pasar al siguiente nivel de la campaña
+    if has_next(game) && window.is_key_pressed(Key::N, KeyRepeat::No) {
+        let next = game.campaign.as_ref().map_or(0, |campaign| campaign.current + 1);
+        if game.start_campaign_level(next) {
+            return Transition::Switch(GameState::Playing);
+        }
+    }
+    if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+        return Transition::Switch(GameState::Menu);
+    }
+    if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+        return Transition::Quit;
+    }
+    Transition::Stay
+}
+
+pub fn render(game: &Game, framebuffer: &mut Framebuffer) {
+    framebuffer.set_background_color(0x000000); // Fondo negro
+    framebuffer.set_current_color(0x00FF00);    // Texto verde
+    framebuffer.draw_text("¡Felicidades! Has completado el nivel.", 100, framebuffer.height / 2, Scale::uniform(48.0), 0x00FF00);
+
+    let elapsed = game.level_time.as_secs_f32();
+    let time_text = match game.level.config.par_time {
+        Some(par) => format!("Tiempo: {:.1} s (par: {:.1} s)", elapsed, par),
+        None => format!("Tiempo: {:.1} s", elapsed),
+    };
+    framebuffer.draw_text(&time_text, 100, framebuffer.height / 2 + 60, Scale::uniform(32.0), 0x00FF00);
+    if has_next(game) {
+        framebuffer.draw_text("Presiona N para el siguiente nivel o Esc para salir.", 100, framebuffer.height / 2 + 110, Scale::uniform(32.0), 0x00FF00);
+    } else {
+        framebuffer.draw_text("Presiona Esc para salir.", 100, framebuffer.height / 2 + 110, Scale::uniform(32.0), 0x00FF00);
+    }
+    framebuffer.draw_text("Enter: volver al menú", 100, framebuffer.height / 2 + 160, Scale::uniform(24.0), 0x00FF00);
+}
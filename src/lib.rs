@@ -0,0 +1,22 @@
+//! Motor del laberinto: carga de niveles, lanzamiento de rayos, dibujo sobre un `Framebuffer`
+//! en memoria y las pantallas del juego. El binario solo abre la ventana de minifb y le pasa
+//! cada frame a `game::Game`.
+
+pub mod color;
+pub mod framebuffer;
+pub mod maze;
+pub mod player;
+pub mod caster;
+pub mod texture;
+pub mod text;
+pub mod lighting;
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(not(feature = "audio"))]
+#[path = "audio_silent.rs"]
+pub mod audio;
+pub mod render;
+pub mod recorder;
+pub mod capture;
+pub mod campaign;
+pub mod game;
@@ -0,0 +1,172 @@
+use nalgebra_glm::Vec2;
+
+use crate::maze::solve::{is_walkable, Cell};
+use crate::maze::Level;
+
+/// Brillo por celda del nivel, calculado en el centro de cada celda e interpolado entre ellas.
+/// La parte estática (alcance, caída y paredes y puertas cerradas que tapan cada luz) se calcula
+/// al cargar el nivel y cada vez que se abre o se cierra una puerta; en cada frame solo se suma
+/// el parpadeo.
+pub struct LightMap {
+    rows: usize,
+    cols: usize,
+    ambient: f32,
+    // Aporte de cada luz a cada celda con el parpadeo apagado, y cuánto parpadea esa luz
+    contributions: Vec<(Vec<f32>, f32)>,
+    // Puertas abiertas cuando se calcularon los aportes, ordenadas
+    open_doors: Vec<Cell>,
+    values: Vec<f32>,
+}
+
+impl LightMap {
+    pub fn new(level: &Level) -> LightMap {
+        let rows = level.maze.len();
+        let cols = level.maze.first().map_or(0, |line| line.len());
+        let ambient = level.config.lighting.ambient;
+
+        let mut light_map = LightMap {
+            rows,
+            cols,
+            ambient,
+            contributions: contributions(level),
+            open_doors: open_doors(level),
+            values: Vec::new(),
+        };
+        light_map.update(0.0);
+        light_map
+    }
+
+    /// Vuelve a calcular qué tapa cada luz si alguna puerta se ha abierto o cerrado desde la
+    /// última vez. El brillo nuevo se aplica en el siguiente `update`.
+    pub fn sync_doors(&mut self, level: &Level) {
+        let open = open_doors(level);
+        if open != self.open_doors {
+            self.contributions = contributions(level);
+            self.open_doors = open;
+        }
+    }
+
+    /// Recalcula el brillo de cada celda para el instante `time` (en segundos) del nivel.
+    pub fn update(&mut self, time: f32) {
+        self.values = vec![self.ambient; self.rows * self.cols];
+        for (index, (weights, flicker)) in self.contributions.iter().enumerate() {
+            let strength = 1.0 - flicker * flicker_noise(time, index as f32);
+            for (value, weight) in self.values.iter_mut().zip(weights) {
+                *value += weight * strength;
+            }
+        }
+    }
+
+    /// Brillo en un punto del mundo (en píxeles), interpolado entre los centros de las celdas vecinas.
+    pub fn at(&self, pos: Vec2, block_size: f32) -> f32 {
+        if self.values.is_empty() {
+            return self.ambient;
+        }
+
+        let x = (pos.x / block_size - 0.5).clamp(0.0, (self.cols - 1) as f32);
+        let y = (pos.y / block_size - 0.5).clamp(0.0, (self.rows - 1) as f32);
+        let (col, row) = (x as usize, y as usize);
+        let (next_col, next_row) = ((col + 1).min(self.cols - 1), (row + 1).min(self.rows - 1));
+        let (fx, fy) = (x - col as f32, y - row as f32);
+
+        let value = |row: usize, col: usize| self.values[row * self.cols + col];
+        let top = value(row, col) + (value(row, next_col) - value(row, col)) * fx;
+        let bottom = value(next_row, col) + (value(next_row, next_col) - value(next_row, col)) * fx;
+        top + (bottom - top) * fy
+    }
+}
+
+fn contributions(level: &Level) -> Vec<(Vec<f32>, f32)> {
+    let rows = level.maze.len();
+    let cols = level.maze.first().map_or(0, |line| line.len());
+
+    level
+        .config
+        .lights
+        .iter()
+        .map(|light| {
+            let mut weights = vec![0.0; rows * cols];
+            for row in 0..rows {
+                for col in 0..cols {
+                    let dr = row as f32 - light.cell.0 as f32;
+                    let dc = col as f32 - light.cell.1 as f32;
+                    let falloff = 1.0 - (dr * dr + dc * dc).sqrt() / light.radius;
+                    if falloff > 0.0 && visible(level, light.cell, (row, col)) {
+                        weights[row * cols + col] = light.intensity * falloff * falloff;
+                    }
+                }
+            }
+            (weights, light.flicker)
+        })
+        .collect()
+}
+
+fn open_doors(level: &Level) -> Vec<Cell> {
+    let mut open: Vec<Cell> = level.doors.iter().filter(|(_, door)| door.is_open()).map(|(&cell, _)| cell).collect();
+    open.sort_unstable();
+    open
+}
+
+/// Una celda ve la luz si entre ambos centros no hay paredes macizas ni puertas cerradas; la
+/// propia celda puede ser una pared, así las caras que dan a la luz quedan iluminadas.
+fn visible(level: &Level, light: Cell, cell: Cell) -> bool {
+    let maze = &level.maze;
+    let from = Vec2::new(light.1 as f32 + 0.5, light.0 as f32 + 0.5);
+    let to = Vec2::new(cell.1 as f32 + 0.5, cell.0 as f32 + 0.5);
+    let steps = (nalgebra_glm::distance(&from, &to) * 8.0).ceil() as usize;
+
+    (1..steps).all(|step| {
+        let point = from + (to - from) * (step as f32 / steps as f32);
+        let crossed = (point.y as usize, point.x as usize);
+        let glyph = maze[crossed.0][crossed.1];
+        let clear = match level.doors.get(&crossed) {
+            Some(door) => door.is_open(),
+            None => is_walkable(glyph),
+        };
+        crossed == light || crossed == cell || clear || level.config.see_through.contains(&glyph)
+    })
+}
+
+/// Ruido suave entre 0 y 1; cada luz usa una fase distinta para no parpadear al unísono.
+fn flicker_noise(time: f32, phase: f32) -> f32 {
+    let phase = phase * 2.39;
+    0.5 + 0.25 * ((time * 7.3 + phase).sin() + (time * 13.1 + phase * 1.7).sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::parse_maze;
+
+    // Una luz a un lado de la puerta y la salida al otro
+    fn corridor(see_through: &str) -> Level {
+        let header = format!(
+            "+++\nsee_through = [{}]\n[lighting]\nambient = 0.2\n[[lights]]\nrow = 2\ncol = 2\nradius = 9.0\n+++\n",
+            see_through
+        );
+        parse_maze(&format!("{}+--+--+--+\n|p  D   g|\n+--+--+--+\n", header)).unwrap()
+    }
+
+    fn beyond_door(level: &Level, lights: &LightMap) -> f32 {
+        lights.at(level.cell_center((1, 6)), level.block_size as f32)
+    }
+
+    #[test]
+    fn closed_doors_block_light() {
+        let mut level = corridor("");
+        let mut lights = LightMap::new(&level);
+        assert_eq!(beyond_door(&level, &lights), 0.2);
+
+        let door = level.doors.get_mut(&(1, 4)).unwrap();
+        door.openness = 1.0;
+        lights.sync_doors(&level);
+        lights.update(0.0);
+        assert!(beyond_door(&level, &lights) > 0.2);
+    }
+
+    #[test]
+    fn see_through_doors_let_light_in() {
+        let level = corridor("\"D\"");
+        assert!(beyond_door(&level, &LightMap::new(&level)) > 0.2);
+    }
+}
@@ -1,290 +1,170 @@
-use minifb::{Key, Window, WindowOptions, MouseMode};
-use nalgebra_glm::{Vec2, distance};
-use std::f32::consts::PI;
-use once_cell::sync::Lazy;
-use std::sync::Arc;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use std::time::{Instant, Duration};
 use rusttype::Scale;
 
-mod framebuffer;
-use framebuffer::Framebuffer;
-mod maze;
-use maze::load_maze;
-
-mod player;
-use player::{Player, process_events};
-
-mod caster;
-use caster::{Intersect, cast_ray};
-
-mod texture;
-use texture::Texture;
-
-mod audio;
-use audio::AudioPlayer;
-
-static WALL2: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/WALL2.jpg")));
-
-static ENEMY: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/sprite.png")));
-
-static SKY: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/sky1.png")));
-
-static GRASS: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/grass.png")));
-
-
-fn cell_to_texture_color(cell: char, tx: u32, ty: u32) -> u32 {
-    //let wall_color = 0x30822e; // Color verde oscuro para las paredes
-    let default_color = 0x000000;
-
-    match cell {
-        '+' | '-' | '|' | 'g' => WALL2.get_pixel_color(tx, ty),
-        _ => default_color,
-    }
+use maze_project::audio::AudioPlayer;
+use maze_project::campaign::Campaign;
+use maze_project::capture;
+use maze_project::framebuffer::Framebuffer;
+use maze_project::game::Game;
+use maze_project::maze::{Level, load_maze, parse_maze};
+use maze_project::maze::generate::{self, Algorithm};
+use maze_project::recorder::{RecordFormat, Recorder};
+
+/// Captura pedida con `--screenshot-after N [--output <archivo>]`: se juegan N frames sin
+/// ventana y se guarda el último.
+struct Capture {
+    frames: usize,
+    output: Option<String>,
 }
 
-fn draw_cell(framebuffer: &mut Framebuffer, xo: usize, yo: usize, block_size: usize, cell: char) {
-    for x in xo..xo + block_size {
-        for y in yo..yo + block_size {
-            let color = match cell {
-                'g' => 0xFF0000, // Rojo para la salida
-                _ => 0x000000,   // Negro para otras celdas
-            };
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y);
-        }
-    }
+/// Opciones que no eligen el nivel sino cómo se juega y se graba.
+struct Options {
+    capture: Option<Capture>,
+    bench_frames: Option<usize>,
+    record_format: RecordFormat,
+    record_fps: f32,
 }
 
-
-fn render3d(framebuffer: &mut Framebuffer, player: &Player, z_buffer: &mut [f32]) {
-    let maze = load_maze("./maze.txt");
-    let num_rays = framebuffer.width;
-    let block_size = 100;
-
-    // Dibujar el fondo con texturas
-    let hh = framebuffer.height as f32 / 2.0;
-
-    // Dibujar la textura del cielo en la mitad superior
-    for i in 0..num_rays {
-        for j in 0..(framebuffer.height / 2) {
-            let tx = (i as f32 / num_rays as f32 * SKY.width as f32) as u32;
-            let ty = (j as f32 / (framebuffer.height / 2) as f32 * SKY.height as f32) as u32;
-            let color = SKY.get_pixel_color(tx, ty);
-            framebuffer.set_current_color(color);
-            framebuffer.point(i, j);
+/// Opciones de línea de comandos: `--level <archivo>` juega ese nivel y
+/// `--generate <algoritmo> [--size ANCHOxALTO] [--seed N]` un laberinto generado;
+/// sin ninguna de las dos se juega la campaña de campaign.toml desde el último nivel desbloqueado.
+/// `--record-format gif|png` y `--record-fps N` configuran la grabación que se activa con F10.
+/// `--bench-frames N` dibuja N frames sin ventana desde el punto de aparición e informa del tiempo.
+fn load_game() -> Result<(Level, Option<Campaign>, Options), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut level_file = None;
+    let mut algorithm = None;
+    let mut size = (8, 6);
+    let mut seed = None;
+    let mut capture_frames = None;
+    let mut capture_output = None;
+    let mut bench_frames = None;
+    let mut record_format = RecordFormat::Gif;
+    let mut record_fps = 15.0;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--level" => level_file = Some(value()?.clone()),
+            "--generate" => algorithm = Some(value()?.parse::<Algorithm>()?),
+            "--size" => {
+                let text = value()?;
+                size = text
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .ok_or_else(|| format!("invalid size '{}', expected WIDTHxHEIGHT", text))?;
+            }
+            "--seed" => {
+                let text = value()?;
+                seed = Some(text.parse::<u64>().map_err(|_| format!("invalid seed '{}'", text))?);
+            }
+            "--screenshot-after" => {
+                let text = value()?;
+                capture_frames = Some(text.parse::<usize>().map_err(|_| format!("invalid frame count '{}'", text))?);
+            }
+            "--output" => capture_output = Some(value()?.clone()),
+            "--bench-frames" => {
+                let text = value()?;
+                bench_frames = Some(
+                    text.parse::<usize>()
+                        .ok()
+                        .filter(|frames| *frames > 0)
+                        .ok_or_else(|| format!("invalid frame count '{}'", text))?,
+                );
+            }
+            "--record-format" => record_format = value()?.parse::<RecordFormat>()?,
+            "--record-fps" => {
+                let text = value()?;
+                record_fps = text
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|fps| *fps >= 1.0)
+                    .ok_or_else(|| format!("invalid frame rate '{}'", text))?;
+            }
+            _ => return Err(format!("unknown argument '{}'", arg)),
         }
     }
 
-    // Dibujar la textura del suelo en la mitad inferior
-    for i in 0..num_rays {
-        for j in (framebuffer.height / 2)..framebuffer.height {
-            let tx = (i as f32 / num_rays as f32 * GRASS.width as f32) as u32;
-            let ty = ((j - framebuffer.height / 2) as f32 / (framebuffer.height / 2) as f32 * GRASS.height as f32) as u32;
-            let color = GRASS.get_pixel_color(tx, ty);
-            framebuffer.set_current_color(color);
-            framebuffer.point(i, j);
-        }
-    }
+    let capture = match (capture_frames, capture_output) {
+        (Some(frames), output) => Some(Capture { frames, output }),
+        (None, Some(_)) => return Err("--output requires --screenshot-after".to_string()),
+        (None, None) => None,
+    };
 
-    for i in 0..num_rays {
-        let current_ray = (i as f32 / num_rays as f32);
-        let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
-        let Intersect = cast_ray(framebuffer, &maze, player, a, block_size, false);
-
-        let distance = Intersect.distance * (a - player.a).cos();
-        let stake_height = (framebuffer.height as f32 / distance) * 70.0;
-        let stake_top = (hh - (stake_height / 2.0)) as usize;
-        let stake_bottom = (hh + (stake_height / 2.0)) as usize;
-
-        z_buffer[i] = distance;
-    
-        for y in stake_top..stake_bottom {
-            let ty = (y as f32 - stake_top as f32) / (stake_bottom as f32 - stake_top as f32) * 128.0;
-            let tx = Intersect.tx;
-            let color = if Intersect.impact == 'g' {
-                0x4c9141 // Verde para la salida
-            } else {
-                cell_to_texture_color(Intersect.impact, tx as u32, ty as u32)
-            };
-            framebuffer.set_current_color(color);
-            framebuffer.point(i, y);
+    let (level, campaign) = match algorithm {
+        Some(algorithm) => {
+            let seed = seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0)
+            });
+            println!("Laberinto {} {}x{} con semilla {}", algorithm, size.0, size.1, seed);
+            let text = generate::generate(size.0, size.1, seed, algorithm);
+            let level = parse_maze(&text).map_err(|err| err.to_string())?;
+            (level, None)
         }
-    }
-}
-
-
-fn render2d(framebuffer: &mut Framebuffer, player: &Player) {
-    let maze = load_maze("./maze.txt");
-    let block_size = 100;
+        None => match level_file {
+            Some(file) => {
+                let level = load_maze(&file).map_err(|err| format!("{}: {}", file, err))?;
+                (level, None)
+            }
+            None => {
+                let campaign = Campaign::load("./campaign.toml", "./progress.toml").map_err(|err| err.to_string())?;
+                let level = campaign
+                    .load_level(campaign.current)
+                    .map_err(|err| format!("{}: {}", campaign.level_path(campaign.current), err))?;
+                (level, Some(campaign))
+            }
+        },
+    };
 
-    for row in 0..maze.len() {
-        for col in 0..maze[row].len() {
-            draw_cell(framebuffer, col * block_size, row * block_size, block_size, maze[row][col]);
-        }
-    }
-    framebuffer.set_current_color(0xFFFFFF);
-    framebuffer.point(player.pos.x as usize, player.pos.y as usize);
-
-    let num_rays = 100;
-    for i in 0..num_rays {
-        let current_ray = (i as f32 / num_rays as f32);
-        let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
-        cast_ray(framebuffer, &maze, player, a, block_size, true);
-    }
+    Ok((level, campaign, Options { capture, bench_frames, record_format, record_fps }))
 }
 
-fn render_minimap(framebuffer: &mut Framebuffer, player: &Player) {
-    let minimap_size = 200; // Tamaño del minimapa
-    let minimap_x = framebuffer.width - minimap_size - 100; // Posición X del minimapa
-    let minimap_y = framebuffer.height - minimap_size - 10; // Posición Y del minimapa
-
-    // Asegúrate de que el minimapa esté dentro de los límites del framebuffer
-    if minimap_x < 0 || minimap_y < 0 {
-        return; // No dibujar si el minimapa está fuera del framebuffer
+/// Empieza una grabación en recordings/ o, si ya había una, la termina y la guarda.
+fn toggle_recording(recording: Option<Recorder>, options: &Options) -> Option<Recorder> {
+    if let Some(recorder) = recording {
+        finish_recording(recorder);
+        return None;
     }
 
-    // Dibujar el fondo del minimapa
-    framebuffer.set_current_color(0x222222); // Color oscuro para el fondo del minimapa
-    for x in minimap_x..minimap_x + minimap_size {
-        for y in minimap_y..minimap_y + minimap_size {
-            if x < framebuffer.width && y < framebuffer.height {
-                framebuffer.point(x, y);
-            }
+    let path = capture::recording_path(options.record_format, capture::timestamp());
+    match Recorder::start(&path, options.record_format, options.record_fps) {
+        Ok(recorder) => {
+            println!("Grabando en {}", path);
+            Some(recorder)
         }
-    }
-
-    // Cargar el laberinto
-    let maze = load_maze("./maze.txt");
-    let block_size = 100; // Tamaño del bloque del mapa
-    let scale = minimap_size as f32 / (maze.len() as f32 * block_size as f32);
-
-    // Dibujar el laberinto en el minimapa
-    for row in 0..maze.len() {
-        for col in 0..maze[row].len() {
-            let cell_x = (col as f32 * block_size as f32 * scale) as usize;
-            let cell_y = (row as f32 * block_size as f32 * scale) as usize;
-            let mini_block_size = (block_size as f32 * scale) as usize;
-
-            // Asegúrate de que las celdas del laberinto no se dibujen fuera de los límites del minimapa
-            for dx in 0..mini_block_size {
-                for dy in 0..mini_block_size {
-                    let x = minimap_x + cell_x + dx;
-                    let y = minimap_y + cell_y + dy;
-                    if x < framebuffer.width && y < framebuffer.height {
-                        let color = if maze[row][col] == 'g' {
-                            0xFF0000 // Rojo para la salida
-                        } else {
-                            cell_to_texture_color(maze[row][col], 0, 0)
-                        };
-                        framebuffer.set_current_color(color);
-                        framebuffer.point(x, y);
-                    }
-                }
-            }
+        Err(err) => {
+            eprintln!("Error al empezar la grabación: {}", err);
+            None
         }
     }
-
-    // Dibujar la posición del jugador en el minimapa
-    framebuffer.set_current_color(0xFF0000); // Color rojo para el jugador
-    let player_x = (player.pos.x as f32 * scale) as usize;
-    let player_y = (player.pos.y as f32 * scale) as usize;
-
-    // Asegúrate de que la posición del jugador esté dentro del minimapa
-    if minimap_x + player_x < framebuffer.width && minimap_y + player_y < framebuffer.height {
-        framebuffer.point(minimap_x + player_x, minimap_y + player_y);
-    }
 }
 
-fn render_enemy(framebuffer: &mut Framebuffer, player: &Player, pos: &Vec2, z_buffer: &mut [f32]) {
-    // player_a
-    let sprite_a = (pos.y - player.pos.y).atan2(pos.x - player.pos.x);
-    // let sprite_a = - player.a;
-    //
-    if sprite_a < 0.0 {
-      return;
-    }
-  
-    let sprite_d = ((player.pos.x - pos.x).powi(2) + (player.pos.y - pos.y).powi(2)).sqrt();
-    // let sprite_d = distance(player.pos, pos);
-  
-    if sprite_d < 10.0 {
-      return;
-    }
-  
-    let screen_height = framebuffer.height as f32;
-    let screen_width = framebuffer.width as f32;
-  
-    let sprite_size = (screen_height / sprite_d) * 100.0;
-    let start_x = (sprite_a - player.a) * (screen_height / player.fov) + (screen_width / 2.0) - (sprite_size / 2.0);
-    let start_y = (screen_height / 2.0) - (sprite_size / 2.0);
-  
-    let end_x = ((start_x + sprite_size) as usize).min(framebuffer.width);
-    let end_y = ((start_y + sprite_size) as usize).min(framebuffer.height);
-    let start_x = start_x.max(0.0) as usize;
-    let start_y = start_y.max(0.0) as usize;
-  
-    if end_x <= 0 {
-      return;
-    }
-  
-    if start_x < framebuffer.width && sprite_d < z_buffer[start_x] {
-      for x in start_x..(end_x - 1) {
-        for y in start_y..(end_y - 1) {
-          let tx = ((x - start_x) * 128 / sprite_size as usize) as u32;
-          let ty = ((y - start_y) * 128 / sprite_size as usize) as u32;
-          let color = ENEMY.get_pixel_color(tx, ty);
-          if color != 0x3a4041 { 
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y);
-          }
-          z_buffer[x] = sprite_d;
+fn finish_recording(recorder: Recorder) {
+    let path = recorder.path().display().to_string();
+    let dropped = recorder.dropped();
+    match recorder.finish() {
+        Ok(frames) if dropped > 0 => {
+            println!("Grabación guardada en {} ({} frames, {} descartados)", path, frames, dropped)
         }
-      }
+        Ok(frames) => println!("Grabación guardada en {} ({} frames)", path, frames),
+        Err(err) => eprintln!("Error al guardar la grabación {}: {}", path, err),
     }
-  }
-  
-  fn render_enemies(framebuffer: &mut Framebuffer, player: &Player, z_buffer: &mut [f32]) {
-    let enemies = vec![
-      Vec2::new(250.0, 250.0),
-      Vec2::new(250.0, 550.0),
-    ];
-  
-    for enemy in &enemies {
-      render_enemy(framebuffer, &player, enemy, z_buffer);
-    }
-  }
-
-  fn has_won(player: &Player, goal_position: &Vec2, block_size: usize) -> bool {
-    let player_block_x = (player.pos.x / block_size as f32).round() as usize;
-    let player_block_y = (player.pos.y / block_size as f32).round() as usize;
-    let goal_block_x = (goal_position.x / block_size as f32).round() as usize;
-    let goal_block_y = (goal_position.y / block_size as f32).round() as usize;
-
-    player_block_x == goal_block_x && player_block_y == goal_block_y
-}
-
-
-fn draw_victory_screen(framebuffer: &mut Framebuffer) {
-    framebuffer.set_background_color(0x000000); // Fondo negro
-    framebuffer.set_current_color(0x00FF00);    // Texto verde
-    framebuffer.draw_text("¡Felicidades! Has completado el nivel.", 100, framebuffer.height / 2, Scale::uniform(48.0), 0x00FF00);
-    framebuffer.draw_text("Presiona Esc para salir.", 100, framebuffer.height / 2 + 60, Scale::uniform(32.0), 0x00FF00);
 }
 
-fn get_goal_position(maze: &[Vec<char>], block_size: usize) -> Vec2 {
-    let mut goal_position = Vec2::new(0.0, 0.0);
-    for (row_idx, row) in maze.iter().enumerate() {
-        for (col_idx, &cell) in row.iter().enumerate() {
-            if cell == 'g' {
-                goal_position = Vec2::new(col_idx as f32 * block_size as f32, row_idx as f32 * block_size as f32);
-                return goal_position;
-            }
-        }
-    }
-    goal_position
+/// Crea la partida o termina el programa si faltan texturas o la música del nivel.
+fn start_game(level: Level, campaign: Option<Campaign>, walking_player: Option<AudioPlayer>) -> Game {
+    Game::new(level, campaign, walking_player).unwrap_or_else(|err| {
+        eprintln!("Error al cargar el nivel: {}", err);
+        std::process::exit(1);
+    })
 }
 
-
 fn main() {
     let window_width = 1200;
     let window_height = 900;
@@ -292,10 +172,38 @@ fn main() {
     let framebuffer_width = 1200;
     let framebuffer_height = 900;
 
-    let frame_delay = Duration::from_millis(0);
-
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
 
+    // Cargar el laberinto una sola vez por nivel
+    let (level, campaign, options) = match load_game() {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("Error al cargar el laberinto: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    // Inicializa valores
+    framebuffer.set_background_color(0x333355);
+
+    if let Some(Capture { frames, output }) = options.capture {
+        let mut game = start_game(level, campaign, None);
+        capture::play_headless(&mut game, &mut framebuffer, frames);
+        match capture::save_screenshot(&framebuffer, output.as_deref()) {
+            Ok(path) => println!("Captura guardada en {}", path),
+            Err(err) => {
+                eprintln!("Error al guardar la captura: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(frames) = options.bench_frames {
+        let mut game = start_game(level, campaign, None);
+        println!("{}", capture::bench(&mut game, &mut framebuffer, frames));
+        return;
+    }
+
     let mut window = Window::new(
         "Rust Graphics - Maze Example",
         window_width,
@@ -307,90 +215,30 @@ fn main() {
     window.set_position(100, 100);
     window.update();
 
-    // Inicializa valores
-    framebuffer.set_background_color(0x333355);
-    let mut player = Player {
-        pos: Vec2::new(150.0, 150.0),
-        a: PI / 3.0,
-        fov: PI / 3.0,
-        velocity: Vec2::new(0.0, 0.0), // Inicializando velocity
-        previous_mouse_pos: Vec2::new(0.0, 0.0), // Inicializando previous_mouse_pos
+    // Sin dispositivo de audio (o compilado sin la feature `audio`) se juega sin sonido
+    let walking_player = match AudioPlayer::new("assets/steps.mp3") {
+        Ok(walking_player) => {
+            walking_player.set_volume(0.5);
+            Some(walking_player)
+        }
+        Err(err) => {
+            eprintln!("Sin sonido: {}", err);
+            None
+        }
     };
-    
-    let mut mode = "3D";
 
-    // Cargar el laberinto y definir block_size
-    let maze = load_maze("./maze.txt");
-    let block_size = 100;
-
-    let goal_position = get_goal_position(&maze, block_size);
+    let mut game = start_game(level, campaign, walking_player);
 
     let mut last_time = Instant::now();
     let mut frame_count = 0;
     let mut fps_text = String::new();
-    let mut last_mouse_x = window.get_mouse_pos(MouseMode::Clamp).unwrap_or((0.0, 0.0)).0;
-
-    let audio_player = AudioPlayer::new("assets/audio1.mp3").expect("Failed when initializing AudioPlayer");
-    audio_player.play();
-
-    let walking_player = AudioPlayer::new("assets/steps.mp3").expect("Failed when initializing AudioPlayer");
-    walking_player.set_volume(0.5);
-
-    // Manejo de pantallas
-    let mut screen = "menu";
+    let mut recording = None;
 
     while window.is_open() {
-        // Escucha de inputs
-        if window.is_key_down(Key::Escape) {
-            break;
-        }
-        if window.is_key_down(Key::M) {
-            mode = if mode == "2D" { "3D" } else { "2D" };
-        }
-
-        process_events(&window, &mut player, &maze, block_size, &walking_player); 
-
         framebuffer.clear();
 
-        match screen {
-            "menu" => {
-                framebuffer.draw_text("Presiona ENTER para comenzar", 400, 450, Scale::uniform(32.0), 0xFFFFFF);
-                if window.is_key_down(Key::Enter) {
-                    screen = "game";
-                }
-            },
-            "game" => {
-                // Captura del movimiento del mouse
-                if let Some((mouse_x, _)) = window.get_mouse_pos(MouseMode::Clamp) {
-                    let mouse_delta_x = mouse_x - last_mouse_x;
-                    player.a += mouse_delta_x * 0.005; // Cambiado a suma para invertir la rotación
-                    last_mouse_x = mouse_x;
-                }
-
-                // Procesar eventos
-                process_events(&window, &mut player, &maze, block_size, &walking_player);
-
-
-                if mode == "2D" {
-                    render2d(&mut framebuffer, &player);
-                } else {
-                    let mut z_buffer = vec![f32::INFINITY; framebuffer.width];
-                    render3d(&mut framebuffer, &player, &mut z_buffer);
-                    render_enemies(&mut framebuffer, &player, &mut z_buffer);
-                }
-
-                // Renderizar el minimapa
-                render_minimap(&mut framebuffer, &player);
-
-                // Verificar condición de victoria
-                if has_won(&player, &goal_position, block_size) {
-                    screen = "win";
-                }
-            },
-            "win" => {
-                draw_victory_screen(&mut framebuffer);
-            },
-            _ => {},
+        if !game.frame(&mut window, &mut framebuffer) {
+            break;
         }
 
         // Calcular FPS
@@ -406,7 +254,25 @@ fn main() {
         }
 
         // Dibujar el texto de FPS en cada frame
-        framebuffer.draw_text(&fps_text, 10, 10, Scale::uniform(32.0), 0xFFFFFF);
+        if game.settings.show_fps {
+            framebuffer.draw_text(&fps_text, 10, 10, Scale::uniform(32.0), 0xFFFFFF);
+        }
+
+        // F12 guarda el frame tal como se ve, con el contador de FPS incluido
+        if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            match capture::save_screenshot(&framebuffer, None) {
+                Ok(path) => println!("Captura guardada en {}", path),
+                Err(err) => eprintln!("Error al guardar la captura: {}", err),
+            }
+        }
+
+        // F10 empieza o termina la grabación de todo lo que se presenta en la ventana
+        if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            recording = toggle_recording(recording, &options);
+        }
+        if let Some(recorder) = &mut recording {
+            recorder.capture(&framebuffer);
+        }
 
         // Actualiza la ventana con el contenido del framebuffer
         window
@@ -415,4 +281,9 @@ fn main() {
 
         std::thread::sleep(Duration::from_millis(16));
     }
-}
\ No newline at end of file
+
+    // Cerrar la ventana con una grabación en marcha también la guarda
+    if let Some(recorder) = recording {
+        finish_recording(recorder);
+    }
+}
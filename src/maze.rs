@@ -1,12 +1,1033 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-pub fn load_maze(filename: &str) -> Vec<Vec<char>> {
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(file);
-
-    reader
-        .lines()
-        .map(|line| line.unwrap().chars().collect())
-        .collect()
-}
\ No newline at end of file
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use nalgebra_glm::Vec2;
+use serde::Deserialize;
+
+pub mod generate;
+pub mod solve;
+
+use solve::Cell;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    Key,
+    Health,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub pos: Vec2,
+}
+
+/// Dirección en la que se extiende la hoja de una puerta, a medio bloque dentro de su celda.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DoorAxis {
+    /// Hoja de este a oeste, cerrando un pasillo norte-sur.
+    EastWest,
+    /// Hoja de norte a sur, cerrando un pasillo este-oeste.
+    NorthSouth,
+}
+
+/// Puerta corredera: 'D' se abre con la tecla de usar y 'L' además gasta una llave.
+#[derive(Clone, Copy, Debug)]
+pub struct Door {
+    pub axis: DoorAxis,
+    pub locked: bool,
+    /// Cuánto se ha deslizado la hoja, de 0 (cerrada) a 1 (abierta).
+    pub openness: f32,
+    pub opening: bool,
+}
+
+impl Door {
+    // Fracción de bloque que se desliza la hoja en cada frame
+    const SPEED: f32 = 0.04;
+
+    /// Solo se puede cruzar cuando la hoja casi ha desaparecido en la pared.
+    pub fn is_open(&self) -> bool {
+        self.openness >= 0.9
+    }
+
+    pub fn update(&mut self) {
+        let target = if self.opening { 1.0 } else { 0.0 };
+        if self.openness < target {
+            self.openness = (self.openness + Door::SPEED).min(target);
+        } else {
+            self.openness = (self.openness - Door::SPEED).max(target);
+        }
+    }
+}
+
+/// Celda de teletransporte ('1' a '9', siempre por parejas). Se entra por su único lado abierto
+/// y se sale por el de la pareja, girado `rotation` radianes.
+#[derive(Clone, Copy, Debug)]
+pub struct Portal {
+    pub exit: Cell,
+    pub rotation: f32,
+}
+
+pub fn is_portal(glyph: char) -> bool {
+    ('1'..='9').contains(&glyph)
+}
+
+fn is_door(glyph: char) -> bool {
+    glyph == 'D' || glyph == 'L'
+}
+
+fn is_wall(glyph: char) -> bool {
+    matches!(glyph, '+' | '-' | '|' | 'g')
+}
+
+/// Rectángulo de celdas (desde 0) con suelo o techo propios.
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub row: usize,
+    pub col: usize,
+    pub width: usize,
+    pub height: usize,
+    pub floor: Option<String>,
+    pub ceiling: Option<String>,
+}
+
+/// Niebla por distancia; las distancias se miden en bloques. No afecta al cielo, que está
+/// infinitamente lejos y se vería siempre del color de la niebla.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fog {
+    Off,
+    /// Sin niebla hasta `start` y opaca desde `end`.
+    Linear { start: f32, end: f32 },
+    Exponential { density: f32 },
+}
+
+impl Fog {
+    /// Fracción de niebla entre 0 (nada) y 1 (solo el color de la niebla) a `distance` bloques.
+    pub fn amount(&self, distance: f32) -> f32 {
+        match *self {
+            Fog::Off => 0.0,
+            Fog::Linear { start, end } if end <= start => if distance < start { 0.0 } else { 1.0 },
+            Fog::Linear { start, end } => ((distance - start) / (end - start)).clamp(0.0, 1.0),
+            Fog::Exponential { density } => 1.0 - (-density * distance).exp(),
+        }
+    }
+}
+
+/// Iluminación del nivel: luz ambiente, sombreado de las caras norte/sur y niebla.
+#[derive(Clone, Copy, Debug)]
+pub struct Lighting {
+    /// Brillo de las zonas a las que no llega ninguna luz (1.0 = nivel completamente iluminado).
+    pub ambient: f32,
+    /// Brillo de las caras norte y sur respecto a las este/oeste (1.0 = sin sombreado).
+    pub side_shade: f32,
+    pub fog: Fog,
+    pub fog_color: u32,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting {
+            ambient: 1.0,
+            side_shade: 0.75,
+            fog: Fog::Off,
+            fog_color: 0x000000,
+        }
+    }
+}
+
+/// Fuente de luz (antorcha, brillo de la salida) en el centro de una celda.
+#[derive(Clone, Copy, Debug)]
+pub struct PointLight {
+    pub cell: Cell,
+    /// Alcance en bloques; la luz se apaga suavemente hasta llegar a cero en ese radio.
+    pub radius: f32,
+    pub intensity: f32,
+    /// Cuánto parpadea, de 0 (luz fija) a 1 (puede apagarse por completo).
+    pub flicker: f32,
+}
+
+/// Metadatos del nivel: en archivos antiguos sin cabecera se usan los valores por defecto.
+#[derive(Clone, Debug)]
+pub struct LevelConfig {
+    pub name: String,
+    pub textures: HashMap<char, String>,
+    /// Altura de las paredes en bloques por glifo; las que no aparecen miden 1.
+    pub heights: HashMap<char, f32>,
+    /// Paredes con huecos (rejas, vallas, ventanas): se ve a través de ellas donde su textura
+    /// es transparente, aunque no se pueden cruzar.
+    pub see_through: HashSet<char>,
+    pub sky: String,
+    pub floor: String,
+    /// Textura de techo; sin ella se ve el cielo panorámico.
+    pub ceiling: Option<String>,
+    /// Sprite de los enemigos; de momento el mismo en todos los niveles.
+    pub enemy_sprite: String,
+    pub regions: Vec<Region>,
+    pub lighting: Lighting,
+    pub lights: Vec<PointLight>,
+    pub music: Option<String>,
+    pub par_time: Option<f32>,
+}
+
+impl LevelConfig {
+    pub fn wall_height(&self, glyph: char) -> f32 {
+        self.heights.get(&glyph).copied().unwrap_or(1.0)
+    }
+
+    /// Altura de la pared más alta del nivel; un rayo puede parar al tocar una así.
+    pub fn tallest_wall(&self) -> f32 {
+        self.heights.values().copied().fold(1.0, f32::max)
+    }
+}
+
+impl Default for LevelConfig {
+    fn default() -> Self {
+        let walls = ['+', '-', '|', 'g'].iter().map(|&glyph| (glyph, "assets/WALL2.jpg"));
+        let doors = ['D', 'L'].iter().map(|&glyph| (glyph, "assets/WALL1.jpg"));
+        let textures = walls.chain(doors).map(|(glyph, path)| (glyph, path.to_string())).collect();
+
+        LevelConfig {
+            name: "Maze".to_string(),
+            textures,
+            heights: HashMap::new(),
+            see_through: HashSet::new(),
+            sky: "assets/sky1.png".to_string(),
+            floor: "assets/grass.png".to_string(),
+            ceiling: None,
+            enemy_sprite: "assets/sprite.png".to_string(),
+            regions: Vec::new(),
+            lighting: Lighting::default(),
+            lights: Vec::new(),
+            music: Some("assets/audio1.mp3".to_string()),
+            par_time: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Level {
+    pub config: LevelConfig,
+    pub maze: Vec<Vec<char>>,
+    pub block_size: usize,
+    pub goal: Vec2,
+    pub spawn: Vec2,
+    pub spawn_angle: f32,
+    pub enemies: Vec<Vec2>,
+    pub items: Vec<Item>,
+    pub doors: HashMap<Cell, Door>,
+    pub portals: HashMap<Cell, Portal>,
+}
+
+impl Level {
+    pub fn new(maze: Vec<Vec<char>>, block_size: usize) -> Self {
+        let goal = get_goal_position(&maze, block_size);
+        // Centro de la primera celda libre, igual que la posición fija que usaba main
+        let spawn = Vec2::new(1.5 * block_size as f32, 1.5 * block_size as f32);
+        let doors = find_doors(&maze);
+        let portals = find_portals(&maze);
+
+        Level {
+            config: LevelConfig::default(),
+            maze,
+            block_size,
+            goal,
+            spawn,
+            spawn_angle: std::f32::consts::PI / 3.0,
+            enemies: Vec::new(),
+            items: Vec::new(),
+            doors,
+            portals,
+        }
+    }
+
+    /// Celda (fila, columna) que contiene una posición del mundo.
+    pub fn cell_at(&self, pos: Vec2) -> Cell {
+        let row = (pos.y.max(0.0) / self.block_size as f32) as usize;
+        let col = (pos.x.max(0.0) / self.block_size as f32) as usize;
+        (row, col)
+    }
+
+    pub fn goal_cell(&self) -> Cell {
+        self.cell_at(self.goal)
+    }
+
+    /// Lleva un punto y una dirección que entran en el portal de `cell` al otro lado,
+    /// conservando la posición y el ángulo relativos a la celda. `None` si no es un portal.
+    pub fn warp(&self, cell: Cell, pos: Vec2, dir: Vec2) -> Option<(Vec2, Vec2)> {
+        let portal = self.portals.get(&cell)?;
+        let offset = nalgebra_glm::rotate_vec2(&(pos - self.cell_center(cell)), portal.rotation);
+        Some((self.cell_center(portal.exit) + offset, nalgebra_glm::rotate_vec2(&dir, portal.rotation)))
+    }
+
+    /// Centro en coordenadas del mundo de una celda.
+    pub fn cell_center(&self, cell: Cell) -> Vec2 {
+        let block_size = self.block_size as f32;
+        Vec2::new((cell.1 as f32 + 0.5) * block_size, (cell.0 as f32 + 0.5) * block_size)
+    }
+}
+
+#[derive(Debug)]
+pub enum MazeError {
+    MissingFile { path: String },
+    Io { path: String, source: io::Error },
+    Empty,
+    RaggedRow { row: usize, expected: usize, found: usize },
+    UnknownGlyph { row: usize, col: usize, glyph: char },
+    MissingGoal,
+    DuplicateSpawn { row: usize, col: usize },
+    InvalidHeader { message: String },
+    /// Una textura o la música del nivel no existe o no se puede leer.
+    Asset { path: String, message: String },
+    BlockedEntity { row: usize, col: usize },
+    LooseDoor { row: usize, col: usize },
+    UnpairedPortal { glyph: char, count: usize },
+    LoosePortal { row: usize, col: usize },
+    Unsolvable,
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MazeError::MissingFile { path } => write!(f, "maze file '{}' not found", path),
+            MazeError::Io { path, source } => write!(f, "cannot read maze file '{}': {}", path, source),
+            MazeError::Empty => write!(f, "maze is empty"),
+            MazeError::RaggedRow { row, expected, found } => {
+                write!(f, "row {} has {} columns, expected {}", row, found, expected)
+            }
+            MazeError::UnknownGlyph { row, col, glyph } => {
+                write!(f, "unknown glyph '{}' at row {}, column {}", glyph, row, col)
+            }
+            MazeError::MissingGoal => write!(f, "maze has no goal cell 'g'"),
+            MazeError::DuplicateSpawn { row, col } => {
+                write!(f, "second spawn marker at row {}, column {}", row, col)
+            }
+            MazeError::InvalidHeader { message } => write!(f, "invalid level header: {}", message),
+            MazeError::Asset { path, message } => write!(f, "cannot load '{}': {}", path, message),
+            MazeError::BlockedEntity { row, col } => {
+                write!(f, "entity at row {}, column {} is not on a floor cell", row, col)
+            }
+            MazeError::LooseDoor { row, col } => {
+                write!(f, "door at row {}, column {} needs walls on two opposite sides", row, col)
+            }
+            MazeError::UnpairedPortal { glyph, count } => {
+                write!(f, "portal '{}' appears {} time(s), expected exactly 2", glyph, count)
+            }
+            MazeError::LoosePortal { row, col } => {
+                write!(f, "portal at row {}, column {} needs exactly one open side", row, col)
+            }
+            MazeError::Unsolvable => write!(f, "the goal 'g' cannot be reached from the spawn point"),
+        }
+    }
+}
+
+impl std::error::Error for MazeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MazeError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+pub fn load_maze(filename: &str) -> Result<Level, MazeError> {
+    let text = fs::read_to_string(filename).map_err(|source| match source.kind() {
+        io::ErrorKind::NotFound => MazeError::MissingFile { path: filename.to_string() },
+        _ => MazeError::Io { path: filename.to_string(), source },
+    })?;
+
+    parse_maze(&text)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Header {
+    name: Option<String>,
+    block_size: Option<usize>,
+    sky: Option<String>,
+    floor: Option<String>,
+    ceiling: Option<String>,
+    music: Option<String>,
+    par_time: Option<f32>,
+    textures: HashMap<String, String>,
+    heights: HashMap<String, f32>,
+    see_through: Vec<String>,
+    regions: Vec<RegionSpec>,
+    entities: Vec<EntitySpec>,
+    lighting: Option<LightingSpec>,
+    fog: Option<FogSpec>,
+    lights: Vec<LightSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegionSpec {
+    row: usize,
+    col: usize,
+    #[serde(default = "one")]
+    width: usize,
+    #[serde(default = "one")]
+    height: usize,
+    floor: Option<String>,
+    ceiling: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightingSpec {
+    ambient: Option<f32>,
+    side_shade: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightSpec {
+    row: usize,
+    col: usize,
+    radius: f32,
+    #[serde(default = "full_intensity")]
+    intensity: f32,
+    #[serde(default)]
+    flicker: f32,
+}
+
+fn full_intensity() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, tag = "mode", rename_all = "lowercase")]
+enum FogSpec {
+    Linear { start: f32, end: f32, color: Option<u32> },
+    Exponential { density: f32, color: Option<u32> },
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum EntityKind {
+    Spawn,
+    Enemy,
+    Key,
+    Health,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EntitySpec {
+    kind: EntityKind,
+    row: usize,
+    col: usize,
+    #[serde(default)]
+    facing: Option<String>,
+}
+
+const HEADER_DELIMITER: &str = "+++";
+
+/// Separa la cabecera TOML opcional (entre dos líneas `+++`) de la cuadrícula.
+/// Devuelve la cabecera, las líneas de la cuadrícula y cuántas líneas ocupa la cabecera.
+/// Las líneas en blanco al final del archivo (las que dejan muchos editores) no son parte de la cuadrícula.
+fn split_header(text: &str) -> Result<(Header, Vec<&str>, usize), MazeError> {
+    let mut lines: Vec<&str> = text.lines().map(|line| line.trim_end_matches('\r')).collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    if lines.first() != Some(&HEADER_DELIMITER) {
+        return Ok((Header::default(), lines, 0));
+    }
+
+    let end = lines
+        .iter()
+        .skip(1)
+        .position(|&line| line == HEADER_DELIMITER)
+        .map(|index| index + 1)
+        .ok_or_else(|| MazeError::InvalidHeader { message: "missing closing '+++'".to_string() })?;
+
+    let header = toml::from_str(&lines[1..end].join("\n"))
+        .map_err(|err| MazeError::InvalidHeader { message: err.to_string() })?;
+
+    Ok((header, lines[end + 1..].to_vec(), end + 1))
+}
+
+fn header_config(header: &Header) -> Result<LevelConfig, MazeError> {
+    let mut config = LevelConfig::default();
+
+    if let Some(name) = &header.name {
+        config.name = name.clone();
+    }
+    if let Some(sky) = &header.sky {
+        config.sky = sky.clone();
+    }
+    if let Some(floor) = &header.floor {
+        config.floor = floor.clone();
+    }
+    config.ceiling = header.ceiling.clone();
+    if header.music.is_some() {
+        config.music = header.music.clone();
+    }
+    config.par_time = header.par_time;
+
+    if let Some(lighting) = &header.lighting {
+        if let Some(ambient) = lighting.ambient {
+            config.lighting.ambient = unit_range("lighting.ambient", ambient)?;
+        }
+        if let Some(side_shade) = lighting.side_shade {
+            config.lighting.side_shade = unit_range("lighting.side_shade", side_shade)?;
+        }
+    }
+    if let Some(fog) = &header.fog {
+        let (mode, color) = match *fog {
+            FogSpec::Linear { start, end, color } => {
+                let (start, end) = (non_negative("fog.start", start)?, non_negative("fog.end", end)?);
+                (Fog::Linear { start, end }, color)
+            }
+            FogSpec::Exponential { density, color } => {
+                (Fog::Exponential { density: non_negative("fog.density", density)? }, color)
+            }
+        };
+        config.lighting.fog = mode;
+        config.lighting.fog_color = color.unwrap_or(config.lighting.fog_color);
+    }
+
+    // Las regiones se escriben con filas y columnas desde 1, como las entidades
+    for region in &header.regions {
+        if region.row == 0 || region.col == 0 {
+            return Err(MazeError::InvalidHeader {
+                message: "region rows and columns start at 1".to_string(),
+            });
+        }
+        config.regions.push(Region {
+            row: region.row - 1,
+            col: region.col - 1,
+            width: region.width,
+            height: region.height,
+            floor: region.floor.clone(),
+            ceiling: region.ceiling.clone(),
+        });
+    }
+
+    // Las luces también van en filas y columnas desde 1; que caigan dentro se comprueba con la cuadrícula
+    for light in &header.lights {
+        if light.row == 0 || light.col == 0 {
+            return Err(MazeError::InvalidHeader {
+                message: "light rows and columns start at 1".to_string(),
+            });
+        }
+        if light.radius <= 0.0 {
+            return Err(MazeError::InvalidHeader {
+                message: "light radius must be positive".to_string(),
+            });
+        }
+        config.lights.push(PointLight {
+            cell: (light.row - 1, light.col - 1),
+            radius: light.radius,
+            intensity: light.intensity,
+            flicker: unit_range("light flicker", light.flicker)?,
+        });
+    }
+
+    for (glyph, path) in &header.textures {
+        config.textures.insert(glyph_key("textures", glyph)?, path.clone());
+    }
+    for (glyph, &height) in &header.heights {
+        if height <= 0.0 {
+            return Err(MazeError::InvalidHeader {
+                message: format!("height of '{}' must be positive", glyph),
+            });
+        }
+        config.heights.insert(glyph_key("heights", glyph)?, height);
+    }
+    for glyph in &header.see_through {
+        config.see_through.insert(glyph_key("see_through", glyph)?);
+    }
+
+    Ok(config)
+}
+
+fn glyph_key(table: &str, key: &str) -> Result<char, MazeError> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(glyph), None) => Ok(glyph),
+        _ => Err(MazeError::InvalidHeader {
+            message: format!("'{}' in {} must be a single glyph", key, table),
+        }),
+    }
+}
+
+fn unit_range(name: &str, value: f32) -> Result<f32, MazeError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(MazeError::InvalidHeader { message: format!("{} must be between 0 and 1", name) })
+    }
+}
+
+fn non_negative(name: &str, value: f32) -> Result<f32, MazeError> {
+    if value >= 0.0 {
+        Ok(value)
+    } else {
+        Err(MazeError::InvalidHeader { message: format!("{} must not be negative", name) })
+    }
+}
+
+fn facing_angle(facing: &str) -> Result<f32, MazeError> {
+    let glyph = match facing {
+        "east" => '>',
+        "south" => 'v',
+        "west" => '<',
+        "north" => '^',
+        _ => {
+            return Err(MazeError::InvalidHeader {
+                message: format!("unknown facing '{}', expected north, south, east or west", facing),
+            })
+        }
+    };
+    Ok(spawn_angle(glyph).unwrap_or(0.0))
+}
+
+/// Parsea un nivel: una cabecera TOML opcional entre líneas `+++` seguida de la cuadrícula ASCII.
+/// Las filas y columnas de los errores son líneas y columnas del archivo, desde 1.
+pub fn parse_maze(text: &str) -> Result<Level, MazeError> {
+    let (header, grid, header_lines) = split_header(text)?;
+    let mut maze: Vec<Vec<char>> = grid.iter().map(|line| line.chars().collect()).collect();
+
+    let config = header_config(&header)?;
+    validate_maze(&maze, header_lines, &config)?;
+
+    let block_size = header.block_size.unwrap_or(100).max(1);
+    let markers = extract_markers(&mut maze, block_size);
+
+    let mut level = Level::new(maze, block_size);
+    level.config = config;
+    if let Some((spawn, spawn_angle)) = markers.spawn {
+        level.spawn = spawn;
+        level.spawn_angle = spawn_angle;
+    }
+    level.enemies = markers.enemies;
+    level.items = markers.items;
+
+    // Entidades de la cabecera, en filas y columnas de la cuadrícula desde 1
+    let mut has_spawn = markers.spawn.is_some();
+    for entity in &header.entities {
+        let cell = (entity.row.wrapping_sub(1), entity.col.wrapping_sub(1));
+        let floor = level.maze.get(cell.0).and_then(|line| line.get(cell.1)) == Some(&' ');
+        if !floor {
+            // Como el resto de errores, en líneas del archivo y no en filas de la cuadrícula
+            return Err(MazeError::BlockedEntity { row: entity.row + header_lines, col: entity.col });
+        }
+
+        let pos = level.cell_center(cell);
+        match entity.kind {
+            EntityKind::Spawn => {
+                // Igual que dos marcadores en la cuadrícula: no se elige uno en silencio
+                if has_spawn {
+                    return Err(MazeError::DuplicateSpawn { row: entity.row + header_lines, col: entity.col });
+                }
+                has_spawn = true;
+                level.spawn = pos;
+                level.spawn_angle = match &entity.facing {
+                    Some(facing) => facing_angle(facing)?,
+                    None => 0.0,
+                };
+            }
+            EntityKind::Enemy => level.enemies.push(pos),
+            EntityKind::Key => level.items.push(Item { kind: ItemKind::Key, pos }),
+            EntityKind::Health => level.items.push(Item { kind: ItemKind::Health, pos }),
+        }
+    }
+
+    let (rows, cols) = (level.maze.len(), level.maze[0].len());
+    for region in &level.config.regions {
+        if region.row.saturating_add(region.height) > rows || region.col.saturating_add(region.width) > cols {
+            return Err(MazeError::InvalidHeader {
+                message: format!(
+                    "region at row {}, col {} ({}x{}) does not fit in the {}x{} maze",
+                    region.row + 1,
+                    region.col + 1,
+                    region.width,
+                    region.height,
+                    cols,
+                    rows
+                ),
+            });
+        }
+    }
+
+    for light in &level.config.lights {
+        if level.maze.get(light.cell.0).and_then(|line| line.get(light.cell.1)).is_none() {
+            return Err(MazeError::InvalidHeader {
+                message: format!("light at row {}, col {} is outside the maze", light.cell.0 + 1, light.cell.1 + 1),
+            });
+        }
+    }
+
+    if solve::solve(&level).is_none() {
+        return Err(MazeError::Unsolvable);
+    }
+
+    Ok(level)
+}
+
+fn validate_maze(maze: &[Vec<char>], first_line: usize, config: &LevelConfig) -> Result<(), MazeError> {
+    if maze.is_empty() || maze[0].is_empty() {
+        return Err(MazeError::Empty);
+    }
+
+    let width = maze[0].len();
+    let mut has_goal = false;
+    let mut has_spawn = false;
+    let mut portal_counts = [0; 9];
+
+    for (index, line) in maze.iter().enumerate() {
+        let row = index + first_line;
+        if line.len() != width {
+            return Err(MazeError::RaggedRow { row: row + 1, expected: width, found: line.len() });
+        }
+
+        for (col, &glyph) in line.iter().enumerate() {
+            match glyph {
+                ' ' | '+' | '-' | '|' => {}
+                'g' => has_goal = true,
+                _ if spawn_angle(glyph).is_some() => {
+                    if has_spawn {
+                        return Err(MazeError::DuplicateSpawn { row: row + 1, col: col + 1 });
+                    }
+                    has_spawn = true;
+                }
+                'e' | 'k' | 'h' => {}
+                _ if is_door(glyph) => {
+                    let wall_at = |r: Option<usize>, c: Option<usize>| {
+                        let glyph = r.zip(c).and_then(|(r, c)| maze.get(r).and_then(|line| line.get(c)));
+                        glyph.is_some_and(|&glyph| is_wall(glyph))
+                    };
+                    let east_west = wall_at(Some(index), col.checked_sub(1)) && wall_at(Some(index), Some(col + 1));
+                    let north_south = wall_at(index.checked_sub(1), Some(col)) && wall_at(Some(index + 1), Some(col));
+                    if !east_west && !north_south {
+                        return Err(MazeError::LooseDoor { row: row + 1, col: col + 1 });
+                    }
+                }
+                _ if is_portal(glyph) => {
+                    if open_sides(maze, (index, col)).len() != 1 {
+                        return Err(MazeError::LoosePortal { row: row + 1, col: col + 1 });
+                    }
+                    portal_counts[glyph as usize - '1' as usize] += 1;
+                }
+                // Cualquier otro glifo con textura en la cabecera es una pared más
+                _ if config.textures.contains_key(&glyph) => {}
+                _ => return Err(MazeError::UnknownGlyph { row: row + 1, col: col + 1, glyph }),
+            }
+        }
+    }
+
+    if !has_goal {
+        return Err(MazeError::MissingGoal);
+    }
+    for (digit, &count) in portal_counts.iter().enumerate() {
+        if count != 0 && count != 2 {
+            let glyph = char::from(b'1' + digit as u8);
+            return Err(MazeError::UnpairedPortal { glyph, count });
+        }
+    }
+
+    Ok(())
+}
+
+/// Ángulo inicial de un marcador de aparición: 'p' y '>' miran al este, 'v' al sur,
+/// '<' al oeste y '^' al norte (el eje y crece hacia abajo).
+fn spawn_angle(glyph: char) -> Option<f32> {
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    match glyph {
+        'p' | '>' => Some(0.0),
+        'v' => Some(FRAC_PI_2),
+        '<' => Some(PI),
+        '^' => Some(-FRAC_PI_2),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Markers {
+    spawn: Option<(Vec2, f32)>,
+    enemies: Vec<Vec2>,
+    items: Vec<Item>,
+}
+
+/// Recoge los marcadores de entidades y los sustituye por suelo.
+fn extract_markers(maze: &mut [Vec<char>], block_size: usize) -> Markers {
+    let mut markers = Markers::default();
+
+    for (row, line) in maze.iter_mut().enumerate() {
+        for (col, glyph) in line.iter_mut().enumerate() {
+            let pos = Vec2::new((col as f32 + 0.5) * block_size as f32, (row as f32 + 0.5) * block_size as f32);
+
+            match *glyph {
+                'e' => markers.enemies.push(pos),
+                'k' => markers.items.push(Item { kind: ItemKind::Key, pos }),
+                'h' => markers.items.push(Item { kind: ItemKind::Health, pos }),
+                other => match spawn_angle(other) {
+                    Some(angle) => markers.spawn = Some((pos, angle)),
+                    None => continue,
+                },
+            }
+
+            *glyph = ' ';
+        }
+    }
+
+    markers
+}
+
+/// Celdas por las que se puede entrar a una casilla: suelo, puertas y marcadores.
+fn is_open(glyph: char) -> bool {
+    matches!(glyph, ' ' | 'e' | 'k' | 'h') || is_door(glyph) || spawn_angle(glyph).is_some()
+}
+
+/// Direcciones (como ángulo, con el eje y hacia abajo) de los vecinos abiertos de una celda.
+fn open_sides(maze: &[Vec<char>], (row, col): Cell) -> Vec<f32> {
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    let glyph_at = |r: Option<usize>, c: Option<usize>| r.zip(c).and_then(|(r, c)| maze.get(r)?.get(c).copied());
+    [
+        (glyph_at(Some(row), Some(col + 1)), 0.0),
+        (glyph_at(Some(row + 1), Some(col)), FRAC_PI_2),
+        (glyph_at(Some(row), col.checked_sub(1)), PI),
+        (glyph_at(row.checked_sub(1), Some(col)), -FRAC_PI_2),
+    ]
+    .into_iter()
+    .filter(|(glyph, _)| glyph.is_some_and(is_open))
+    .map(|(_, angle)| angle)
+    .collect()
+}
+
+/// Une cada portal con su pareja. Quien entra lo hace en sentido contrario al lado abierto
+/// del portal y sale en el sentido del lado abierto de la pareja.
+fn find_portals(maze: &[Vec<char>]) -> HashMap<Cell, Portal> {
+    let mut by_glyph: HashMap<char, Vec<Cell>> = HashMap::new();
+    for (row, line) in maze.iter().enumerate() {
+        for (col, &glyph) in line.iter().enumerate() {
+            if is_portal(glyph) {
+                by_glyph.entry(glyph).or_default().push((row, col));
+            }
+        }
+    }
+
+    let mut portals = HashMap::new();
+    for cells in by_glyph.values() {
+        let &[a, b] = cells.as_slice() else {
+            continue;
+        };
+        let (Some(&facing_a), Some(&facing_b)) = (open_sides(maze, a).first(), open_sides(maze, b).first()) else {
+            continue;
+        };
+        let turn = facing_b - facing_a + std::f32::consts::PI;
+        portals.insert(a, Portal { exit: b, rotation: turn });
+        portals.insert(b, Portal { exit: a, rotation: -turn });
+    }
+
+    portals
+}
+
+/// Puertas de la cuadrícula, todas cerradas; la hoja va entre las dos paredes que la sujetan.
+fn find_doors(maze: &[Vec<char>]) -> HashMap<Cell, Door> {
+    let mut doors = HashMap::new();
+
+    for (row, line) in maze.iter().enumerate() {
+        for (col, &glyph) in line.iter().enumerate() {
+            if !is_door(glyph) {
+                continue;
+            }
+            let west = col.checked_sub(1).and_then(|c| line.get(c));
+            let east = line.get(col + 1);
+            let axis = if west.is_some_and(|&g| is_wall(g)) && east.is_some_and(|&g| is_wall(g)) {
+                DoorAxis::EastWest
+            } else {
+                DoorAxis::NorthSouth
+            };
+            doors.insert((row, col), Door { axis, locked: glyph == 'L', openness: 0.0, opening: false });
+        }
+    }
+
+    doors
+}
+
+fn get_goal_position(maze: &[Vec<char>], block_size: usize) -> Vec2 {
+    for (row_idx, row) in maze.iter().enumerate() {
+        for (col_idx, &cell) in row.iter().enumerate() {
+            if cell == 'g' {
+                return Vec2::new(col_idx as f32 * block_size as f32, row_idx as f32 * block_size as f32);
+            }
+        }
+    }
+    Vec2::new(0.0, 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "+++\nname = \"Prueba\"\n+++\n";
+
+    fn error(text: &str) -> MazeError {
+        match parse_maze(text) {
+            Ok(_) => panic!("maze should be rejected:\n{}", text),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn missing_file() {
+        let err = load_maze("no/existe.txt").err().unwrap();
+        assert!(matches!(err, MazeError::MissingFile { ref path } if path == "no/existe.txt"));
+        assert_eq!(err.to_string(), "maze file 'no/existe.txt' not found");
+    }
+
+    #[test]
+    fn unreadable_file() {
+        // Un directorio existe pero no se puede leer como texto
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let err = load_maze(dir).err().unwrap();
+        assert!(matches!(err, MazeError::Io { ref path, .. } if path == dir));
+        assert!(err.to_string().starts_with(&format!("cannot read maze file '{}': ", dir)));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(matches!(error(""), MazeError::Empty));
+        assert!(matches!(error(HEADER), MazeError::Empty));
+        assert_eq!(MazeError::Empty.to_string(), "maze is empty");
+    }
+
+    #[test]
+    fn ragged_row() {
+        let err = error("+--+--+\n|p   g|\n+--+\n");
+        assert!(matches!(err, MazeError::RaggedRow { row: 3, expected: 7, found: 4 }));
+        assert_eq!(err.to_string(), "row 3 has 4 columns, expected 7");
+
+        let err = error(&format!("{}+--+--+\n|p   g|\n+--+\n", HEADER));
+        assert!(matches!(err, MazeError::RaggedRow { row: 6, .. }));
+    }
+
+    #[test]
+    fn unknown_glyph() {
+        let err = error("+--+--+\n|p ? g|\n+--+--+\n");
+        assert!(matches!(err, MazeError::UnknownGlyph { row: 2, col: 4, glyph: '?' }));
+        assert_eq!(err.to_string(), "unknown glyph '?' at row 2, column 4");
+
+        let err = error(&format!("{}+--+--+\n|p ? g|\n+--+--+\n", HEADER));
+        assert!(matches!(err, MazeError::UnknownGlyph { row: 5, col: 4, .. }));
+    }
+
+    #[test]
+    fn missing_goal() {
+        let err = error("+--+--+\n|p    |\n+--+--+\n");
+        assert!(matches!(err, MazeError::MissingGoal));
+        assert_eq!(err.to_string(), "maze has no goal cell 'g'");
+    }
+
+    #[test]
+    fn duplicate_spawn() {
+        let err = error("+--+--+\n|p  >g|\n+--+--+\n");
+        assert!(matches!(err, MazeError::DuplicateSpawn { row: 2, col: 5 }));
+        assert_eq!(err.to_string(), "second spawn marker at row 2, column 5");
+
+        let err = error(&format!("{}+--+--+\n|p  >g|\n+--+--+\n", HEADER));
+        assert!(matches!(err, MazeError::DuplicateSpawn { row: 5, col: 5 }));
+    }
+
+    #[test]
+    fn trailing_blank_lines() {
+        let level = parse_maze("+--+--+\n|p   g|\n+--+--+\n\n  \r\n\n").unwrap();
+        assert_eq!(level.maze.len(), 3);
+        assert!(parse_maze(&format!("{}+--+--+\n|p   g|\n+--+--+\n\n", HEADER)).is_ok());
+
+        // Una línea vacía en medio de la cuadrícula sigue siendo un error
+        let err = error("+--+--+\n\n|p   g|\n+--+--+\n");
+        assert!(matches!(err, MazeError::RaggedRow { row: 2, expected: 7, found: 0 }));
+    }
+
+    #[test]
+    fn header_spawn_and_grid_spawn() {
+        let spawn = "+++\n[[entities]]\nkind = \"spawn\"\nrow = 2\ncol = 4\n+++\n";
+        let err = error(&format!("{}+--+--+\n|p   g|\n+--+--+\n", spawn));
+        assert!(matches!(err, MazeError::DuplicateSpawn { row: 8, col: 4 }));
+        assert_eq!(err.to_string(), "second spawn marker at row 8, column 4");
+
+        let twice = format!("{}{}", spawn.trim_end_matches("+++\n"), "[[entities]]\nkind = \"spawn\"\nrow = 2\ncol = 3\n+++\n");
+        assert!(matches!(error(&format!("{}+--+--+\n|    g|\n+--+--+\n", twice)), MazeError::DuplicateSpawn { .. }));
+
+        let level = parse_maze(&format!("{}+--+--+\n|    g|\n+--+--+\n", spawn)).unwrap();
+        assert_eq!(level.cell_at(level.spawn), (1, 3));
+    }
+
+    #[test]
+    fn invalid_header() {
+        let err = error("+++\nname = \"Prueba\"\n+--+--+\n|p   g|\n+--+--+\n");
+        assert!(matches!(err, MazeError::InvalidHeader { .. }));
+        assert_eq!(err.to_string(), "invalid level header: missing closing '+++'");
+
+        let err = error("+++\n[lighting]\nambient = 2.0\n+++\n+--+--+\n|p   g|\n+--+--+\n");
+        assert_eq!(err.to_string(), "invalid level header: lighting.ambient must be between 0 and 1");
+
+        let err = error("+++\n[fog]\nmode = \"exponential\"\ndensity = -0.5\n+++\n+--+--+\n|p   g|\n+--+--+\n");
+        assert_eq!(err.to_string(), "invalid level header: fog.density must not be negative");
+        let err = error("+++\n[fog]\nmode = \"linear\"\nstart = -1.0\nend = 4.0\n+++\n+--+--+\n|p   g|\n+--+--+\n");
+        assert_eq!(err.to_string(), "invalid level header: fog.start must not be negative");
+    }
+
+    #[test]
+    fn region_outside_the_maze() {
+        let region = "+++\n[[regions]]\nrow = 2\ncol = 2\nwidth = 100000\nheight = 100000\nfloor = \"x.png\"\n+++\n";
+        let err = error(&format!("{}+--+--+\n|p   g|\n+--+--+\n", region));
+        assert_eq!(
+            err.to_string(),
+            "invalid level header: region at row 2, col 2 (100000x100000) does not fit in the 7x3 maze"
+        );
+
+        let region = "+++\n[[regions]]\nrow = 2\ncol = 2\nwidth = 5\n+++\n";
+        assert!(parse_maze(&format!("{}+--+--+\n|p   g|\n+--+--+\n", region)).is_ok());
+    }
+
+    #[test]
+    fn blocked_entity() {
+        // La fila 1 de la cuadrícula es la línea 7 del archivo, detrás de las seis de la cabecera
+        let text = "+++\n[[entities]]\nkind = \"enemy\"\nrow = 1\ncol = 2\n+++\n+--+--+\n|p   g|\n+--+--+\n";
+        let err = error(text);
+        assert!(matches!(err, MazeError::BlockedEntity { row: 7, col: 2 }));
+        assert_eq!(err.to_string(), "entity at row 7, column 2 is not on a floor cell");
+    }
+
+    #[test]
+    fn loose_door() {
+        let err = error("+--+--+\n|p    |\n|  D g|\n+--+--+\n");
+        assert!(matches!(err, MazeError::LooseDoor { row: 3, col: 4 }));
+        assert_eq!(err.to_string(), "door at row 3, column 4 needs walls on two opposite sides");
+    }
+
+    #[test]
+    fn unpaired_portal() {
+        let err = error("+--+-+\n|p 1g|\n+--+-+\n");
+        assert!(matches!(err, MazeError::UnpairedPortal { glyph: '1', count: 1 }));
+        assert_eq!(err.to_string(), "portal '1' appears 1 time(s), expected exactly 2");
+    }
+
+    #[test]
+    fn loose_portal() {
+        let err = error("+--+--+\n|p 1 g|\n+--+--+\n");
+        assert!(matches!(err, MazeError::LoosePortal { row: 2, col: 4 }));
+        assert_eq!(err.to_string(), "portal at row 2, column 4 needs exactly one open side");
+    }
+
+    #[test]
+    fn unsolvable() {
+        let err = error("+--+--+\n|p |  |\n+--+ g|\n+--+--+\n");
+        assert!(matches!(err, MazeError::Unsolvable));
+        assert_eq!(err.to_string(), "the goal 'g' cannot be reached from the spawn point");
+
+        // La puerta con llave no se puede abrir sin ninguna llave en el nivel
+        assert!(matches!(error("+--+--+\n|p L g|\n+--+--+\n"), MazeError::Unsolvable));
+    }
+}
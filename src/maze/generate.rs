@@ -0,0 +1,439 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    RecursiveBacktracker,
+    Prim,
+    Kruskal,
+    Wilson,
+    Eller,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 5] = [
+        Algorithm::RecursiveBacktracker,
+        Algorithm::Prim,
+        Algorithm::Kruskal,
+        Algorithm::Wilson,
+        Algorithm::Eller,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::RecursiveBacktracker => "backtracker",
+            Algorithm::Prim => "prim",
+            Algorithm::Kruskal => "kruskal",
+            Algorithm::Wilson => "wilson",
+            Algorithm::Eller => "eller",
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.name() == s.to_ascii_lowercase())
+            .ok_or_else(|| format!("unknown maze algorithm '{}'", s))
+    }
+}
+
+/// Genera un laberinto de `width` x `height` celdas en el formato ASCII de maze.txt.
+/// La salida 'g' queda en la celda inferior derecha y el jugador empieza en la superior izquierda.
+/// La misma semilla produce siempre exactamente el mismo texto.
+pub fn generate(width: usize, height: usize, seed: u64, algorithm: Algorithm) -> String {
+    let width = width.max(1);
+    let height = height.max(1);
+    let mut grid = Grid::new(width, height);
+    let mut rng = Rng::new(seed);
+
+    match algorithm {
+        Algorithm::RecursiveBacktracker => recursive_backtracker(&mut grid, &mut rng),
+        Algorithm::Prim => prim(&mut grid, &mut rng),
+        Algorithm::Kruskal => kruskal(&mut grid, &mut rng),
+        Algorithm::Wilson => wilson(&mut grid, &mut rng),
+        Algorithm::Eller => eller(&mut grid, &mut rng),
+    }
+
+    grid.to_ascii()
+}
+
+/// SplitMix64: pequeño y estable, así un nivel compartido por semilla no depende
+/// de la versión de ninguna crate externa.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+struct Grid {
+    width: usize,
+    height: usize,
+    open_east: Vec<bool>,
+    open_south: Vec<bool>,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            open_east: vec![false; width * height],
+            open_south: vec![false; width * height],
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    fn neighbors(&self, cell: usize) -> Vec<usize> {
+        let (x, y) = (cell % self.width, cell / self.width);
+        let mut result = Vec::with_capacity(4);
+        if y > 0 {
+            result.push(cell - self.width);
+        }
+        if x + 1 < self.width {
+            result.push(cell + 1);
+        }
+        if y + 1 < self.height {
+            result.push(cell + self.width);
+        }
+        if x > 0 {
+            result.push(cell - 1);
+        }
+        result
+    }
+
+    fn carve(&mut self, a: usize, b: usize) {
+        let (a, b) = (a.min(b), a.max(b));
+        // Por la fila y no por `b == a + 1`: con una sola columna, la celda de abajo es a + 1
+        if a / self.width == b / self.width {
+            self.open_east[a] = true;
+        } else {
+            self.open_south[a] = true;
+        }
+    }
+
+    fn to_ascii(&self) -> String {
+        let mut out = String::new();
+
+        out.push('+');
+        for _ in 0..self.width {
+            out.push_str("--+");
+        }
+        out.push('\n');
+
+        for y in 0..self.height {
+            out.push('|');
+            for x in 0..self.width {
+                let cell = y * self.width + x;
+                let is_goal = x == self.width - 1 && y == self.height - 1;
+                out.push_str(if is_goal { " g" } else { "  " });
+                out.push(if self.open_east[cell] { ' ' } else { '|' });
+            }
+            out.push('\n');
+
+            out.push('+');
+            for x in 0..self.width {
+                let cell = y * self.width + x;
+                out.push_str(if self.open_south[cell] { "  " } else { "--" });
+                out.push('+');
+            }
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+fn recursive_backtracker(grid: &mut Grid, rng: &mut Rng) {
+    let mut visited = vec![false; grid.len()];
+    let mut stack = vec![0];
+    visited[0] = true;
+
+    while let Some(&cell) = stack.last() {
+        let unvisited: Vec<usize> = grid.neighbors(cell).into_iter().filter(|&n| !visited[n]).collect();
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let next = unvisited[rng.below(unvisited.len())];
+        grid.carve(cell, next);
+        visited[next] = true;
+        stack.push(next);
+    }
+}
+
+fn prim(grid: &mut Grid, rng: &mut Rng) {
+    let mut in_maze = vec![false; grid.len()];
+    let mut in_frontier = vec![false; grid.len()];
+    let mut frontier = Vec::new();
+
+    let start = rng.below(grid.len());
+    in_maze[start] = true;
+    for n in grid.neighbors(start) {
+        in_frontier[n] = true;
+        frontier.push(n);
+    }
+
+    while !frontier.is_empty() {
+        let cell = frontier.swap_remove(rng.below(frontier.len()));
+
+        let connected: Vec<usize> = grid.neighbors(cell).into_iter().filter(|&n| in_maze[n]).collect();
+        let target = connected[rng.below(connected.len())];
+        grid.carve(cell, target);
+        in_maze[cell] = true;
+
+        for n in grid.neighbors(cell) {
+            if !in_maze[n] && !in_frontier[n] {
+                in_frontier[n] = true;
+                frontier.push(n);
+            }
+        }
+    }
+}
+
+fn kruskal(grid: &mut Grid, rng: &mut Rng) {
+    let mut edges = Vec::new();
+    for cell in 0..grid.len() {
+        let (x, y) = (cell % grid.width, cell / grid.width);
+        if x + 1 < grid.width {
+            edges.push((cell, cell + 1));
+        }
+        if y + 1 < grid.height {
+            edges.push((cell, cell + grid.width));
+        }
+    }
+    rng.shuffle(&mut edges);
+
+    let mut parent: Vec<usize> = (0..grid.len()).collect();
+    fn find(parent: &mut [usize], mut cell: usize) -> usize {
+        while parent[cell] != cell {
+            parent[cell] = parent[parent[cell]];
+            cell = parent[cell];
+        }
+        cell
+    }
+
+    for (a, b) in edges {
+        let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+        if root_a != root_b {
+            parent[root_b] = root_a;
+            grid.carve(a, b);
+        }
+    }
+}
+
+fn wilson(grid: &mut Grid, rng: &mut Rng) {
+    let mut in_maze = vec![false; grid.len()];
+    in_maze[rng.below(grid.len())] = true;
+    // Para cada celda, el último paso dado desde ella: sobrescribirlo borra los bucles
+    let mut next = vec![0; grid.len()];
+
+    for start in 0..grid.len() {
+        if in_maze[start] {
+            continue;
+        }
+
+        let mut cell = start;
+        while !in_maze[cell] {
+            let neighbors = grid.neighbors(cell);
+            next[cell] = neighbors[rng.below(neighbors.len())];
+            cell = next[cell];
+        }
+
+        let mut cell = start;
+        while !in_maze[cell] {
+            in_maze[cell] = true;
+            grid.carve(cell, next[cell]);
+            cell = next[cell];
+        }
+    }
+}
+
+fn eller(grid: &mut Grid, rng: &mut Rng) {
+    let width = grid.width;
+    let mut sets: Vec<Option<usize>> = vec![None; width];
+    let mut next_set = 0;
+
+    for y in 0..grid.height {
+        let last_row = y + 1 == grid.height;
+
+        for set in sets.iter_mut() {
+            if set.is_none() {
+                *set = Some(next_set);
+                next_set += 1;
+            }
+        }
+
+        for x in 0..width - 1 {
+            if sets[x] != sets[x + 1] && (last_row || rng.coin()) {
+                let (keep, merged) = (sets[x], sets[x + 1]);
+                for set in sets.iter_mut().filter(|set| **set == merged) {
+                    *set = keep;
+                }
+                grid.carve(y * width + x, y * width + x + 1);
+            }
+        }
+
+        if last_row {
+            break;
+        }
+
+        let mut next_sets = vec![None; width];
+        let mut seen = Vec::new();
+        for x in 0..width {
+            if seen.contains(&sets[x]) {
+                continue;
+            }
+            seen.push(sets[x]);
+
+            let members: Vec<usize> = (0..width).filter(|&m| sets[m] == sets[x]).collect();
+            let forced = members[rng.below(members.len())];
+            for m in members {
+                if m == forced || rng.coin() {
+                    grid.carve(y * width + m, (y + 1) * width + m);
+                    next_sets[m] = sets[m];
+                }
+            }
+        }
+        sets = next_sets;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::parse_maze;
+
+    // Salida de generate(4, 3, 7, ...) al escribir estas pruebas: si cambia, las semillas que ya se
+    // compartieron dejan de dar el mismo laberinto
+    const FIXTURES: [(Algorithm, &str); 5] = [
+        (
+            Algorithm::RecursiveBacktracker,
+            "\
++--+--+--+--+
+|  |        |
++  +  +--+  +
+|     |  |  |
++--+--+  +  +
+|          g|
++--+--+--+--+
+",
+        ),
+        (
+            Algorithm::Prim,
+            "\
++--+--+--+--+
+|     |     |
++--+  +  +  +
+|        |  |
++--+--+  +--+
+|          g|
++--+--+--+--+
+",
+        ),
+        (
+            Algorithm::Kruskal,
+            "\
++--+--+--+--+
+|  |        |
++  +--+  +--+
+|     |     |
++  +--+--+  +
+|          g|
++--+--+--+--+
+",
+        ),
+        (
+            Algorithm::Wilson,
+            "\
++--+--+--+--+
+|           |
++--+  +--+  +
+|     |  |  |
++--+--+  +  +
+|          g|
++--+--+--+--+
+",
+        ),
+        (
+            Algorithm::Eller,
+            "\
++--+--+--+--+
+|     |  |  |
++--+  +  +  +
+|  |        |
++  +--+  +--+
+|          g|
++--+--+--+--+
+",
+        ),
+    ];
+
+    #[test]
+    fn same_seed_same_text() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(generate(12, 9, 42, algorithm), generate(12, 9, 42, algorithm), "{}", algorithm);
+        }
+    }
+
+    #[test]
+    fn seeds_are_stable() {
+        for (algorithm, expected) in FIXTURES {
+            assert_eq!(generate(4, 3, 7, algorithm), expected, "{}", algorithm);
+        }
+    }
+
+    #[test]
+    fn every_algorithm_produces_a_valid_maze() {
+        let sizes = [(1, 1), (1, 2), (1, 7), (2, 1), (7, 1), (2, 2), (3, 5), (5, 3), (10, 10), (16, 9)];
+        for algorithm in Algorithm::ALL {
+            for &(width, height) in &sizes {
+                for seed in 0..4 {
+                    let text = generate(width, height, seed, algorithm);
+                    if let Err(err) = parse_maze(&text) {
+                        panic!("{} {}x{} seed {}: {}\n{}", algorithm, width, height, seed, err, text);
+                    }
+                }
+            }
+        }
+    }
+}
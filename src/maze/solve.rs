@@ -0,0 +1,385 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use super::{is_portal, ItemKind, Level};
+
+/// Celda de la cuadrícula del laberinto como (fila, columna).
+pub type Cell = (usize, usize);
+
+/// Celdas por las que se puede pasar; las puertas cuentan aunque estén cerradas o con llave,
+/// y los portales llevan a su pareja.
+pub fn is_walkable(glyph: char) -> bool {
+    matches!(glyph, ' ' | 'D' | 'L') || is_portal(glyph)
+}
+
+/// La otra celda con el mismo glifo de portal.
+fn partner(maze: &[Vec<char>], cell: Cell) -> Option<Cell> {
+    let glyph = maze[cell.0][cell.1];
+    maze.iter().enumerate().find_map(|(row, line)| {
+        line.iter()
+            .enumerate()
+            .find(|&(col, &other)| other == glyph && (row, col) != cell)
+            .map(|(col, _)| (row, col))
+    })
+}
+
+/// Vecinos transitables de `cell`; la meta se acepta aunque su glifo sea de pared, como 'g'.
+fn neighbors(maze: &[Vec<char>], cell: Cell, goal: Cell) -> Vec<Cell> {
+    let (row, col) = cell;
+    let mut candidates = vec![(row + 1, col), (row, col + 1)];
+    if is_portal(maze[row][col]) {
+        candidates.extend(partner(maze, cell));
+    }
+    if row > 0 {
+        candidates.push((row - 1, col));
+    }
+    if col > 0 {
+        candidates.push((row, col - 1));
+    }
+
+    candidates
+        .into_iter()
+        .filter(|&(r, c)| {
+            let glyph = maze.get(r).and_then(|line| line.get(c));
+            (r, c) == goal || glyph.is_some_and(|&glyph| is_walkable(glyph))
+        })
+        .collect()
+}
+
+fn rebuild_path(came_from: &[Vec<Option<Cell>>], start: Cell, goal: Cell) -> Vec<Cell> {
+    let mut path = vec![goal];
+    let mut cell = goal;
+    while cell != start {
+        cell = came_from[cell.0][cell.1].expect("every reached cell has a predecessor");
+        path.push(cell);
+    }
+    path.reverse();
+    path
+}
+
+fn visited_grid(maze: &[Vec<char>]) -> Vec<Vec<Option<Cell>>> {
+    let width = maze.iter().map(|line| line.len()).max().unwrap_or(0);
+    vec![vec![None; width]; maze.len()]
+}
+
+fn in_bounds(maze: &[Vec<char>], cell: Cell) -> bool {
+    cell.0 < maze.len() && cell.1 < maze[cell.0].len()
+}
+
+/// Camino más corto de `start` a `goal` por búsqueda en anchura, ambos extremos incluidos.
+pub fn bfs(maze: &[Vec<char>], start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+    if !in_bounds(maze, start) || !in_bounds(maze, goal) {
+        return None;
+    }
+
+    let mut came_from = visited_grid(maze);
+    came_from[start.0][start.1] = Some(start);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(cell) = queue.pop_front() {
+        if cell == goal {
+            return Some(rebuild_path(&came_from, start, goal));
+        }
+
+        for next in neighbors(maze, cell, goal) {
+            if came_from[next.0][next.1].is_none() {
+                came_from[next.0][next.1] = Some(cell);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// Igual que `bfs` pero guiado por la distancia Manhattan; explora menos celdas en mapas grandes.
+/// Con portales la estimación es la menor entre ir directo y ir al portal más cercano, saltar
+/// y caminar desde la salida más cercana a la meta, así nunca se pasa y el camino sigue siendo
+/// el más corto.
+pub fn astar(maze: &[Vec<char>], start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+    if !in_bounds(maze, start) || !in_bounds(maze, goal) {
+        return None;
+    }
+
+    let manhattan = |a: Cell, b: Cell| a.0.abs_diff(b.0) + a.1.abs_diff(b.1);
+    let portals: Vec<Cell> = maze
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| line.iter().enumerate().filter(|(_, &glyph)| is_portal(glyph)).map(move |(col, _)| (row, col)))
+        .collect();
+    let exit_to_goal = portals.iter().map(|&portal| manhattan(portal, goal)).min();
+    let heuristic = |cell: Cell| {
+        let direct = manhattan(cell, goal);
+        match exit_to_goal {
+            Some(exit_to_goal) => {
+                let to_portal = portals.iter().map(|&portal| manhattan(cell, portal)).min().unwrap_or(0);
+                direct.min(to_portal + 1 + exit_to_goal)
+            }
+            None => direct,
+        }
+    };
+
+    let mut came_from = visited_grid(maze);
+    let mut cost = vec![vec![usize::MAX; came_from.first().map_or(0, |row| row.len())]; maze.len()];
+    came_from[start.0][start.1] = Some(start);
+    cost[start.0][start.1] = 0;
+
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((heuristic(start), start)));
+
+    while let Some(Reverse((_, cell))) = open.pop() {
+        if cell == goal {
+            return Some(rebuild_path(&came_from, start, goal));
+        }
+
+        let next_cost = cost[cell.0][cell.1] + 1;
+        for next in neighbors(maze, cell, goal) {
+            if next_cost < cost[next.0][next.1] {
+                cost[next.0][next.1] = next_cost;
+                came_from[next.0][next.1] = Some(cell);
+                open.push(Reverse((next_cost + heuristic(next), next)));
+            }
+        }
+    }
+
+    None
+}
+
+/// Posición en la búsqueda con llaves: la celda, las llaves recogidas y las puertas 'L' ya abiertas
+/// (un bit por llave y por puerta).
+type KeyState = (Cell, u64, u64);
+
+/// Camino desde el punto de aparición del nivel hasta su celda 'g'. A diferencia de `bfs`, una
+/// puerta con llave solo se cruza después de recoger una llave que no se haya gastado en otra.
+pub fn solve(level: &Level) -> Option<Vec<Cell>> {
+    let maze = &level.maze;
+    let (start, goal) = (level.cell_at(level.spawn), level.goal_cell());
+    if !in_bounds(maze, start) || !in_bounds(maze, goal) {
+        return None;
+    }
+
+    let keys: Vec<Cell> = level
+        .items
+        .iter()
+        .filter(|item| item.kind == ItemKind::Key)
+        .map(|item| level.cell_at(item.pos))
+        .collect();
+    let mut locks: Vec<Cell> = level.doors.iter().filter(|(_, door)| door.locked).map(|(&cell, _)| cell).collect();
+    locks.sort_unstable();
+    if keys.len() > 64 || locks.len() > 64 {
+        // Demasiadas para las máscaras: basta con que haya llaves para todas las puertas
+        return (keys.len() >= locks.len()).then(|| astar(maze, start, goal)).flatten();
+    }
+
+    let pick_up = |cell: Cell, picked: u64| {
+        keys.iter().enumerate().filter(|&(_, &key)| key == cell).fold(picked, |picked, (index, _)| picked | 1 << index)
+    };
+
+    let first = (start, pick_up(start, 0), 0);
+    let mut came_from: HashMap<KeyState, KeyState> = HashMap::from([(first, first)]);
+    let mut queue = VecDeque::from([first]);
+
+    while let Some(state) = queue.pop_front() {
+        let (cell, picked, opened) = state;
+        if cell == goal {
+            let mut path = vec![cell];
+            let mut state = state;
+            while state != first {
+                state = came_from[&state];
+                path.push(state.0);
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for next in neighbors(maze, cell, goal) {
+            let mut opened = opened;
+            if let Some(lock) = locks.iter().position(|&lock| lock == next) {
+                if opened & 1 << lock == 0 {
+                    if picked.count_ones() <= opened.count_ones() {
+                        continue;
+                    }
+                    opened |= 1 << lock;
+                }
+            }
+
+            let next_state = (next, pick_up(next, picked), opened);
+            if let Entry::Vacant(entry) = came_from.entry(next_state) {
+                entry.insert(state);
+                queue.push_back(next_state);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::generate::{generate, Algorithm};
+
+    fn grid(text: &str) -> Vec<Vec<char>> {
+        text.lines().map(|line| line.chars().collect()).collect()
+    }
+
+    // Dos caminos de 12 celdas a la meta, por arriba y por la izquierda, y un callejón en el centro
+    const FORK: &str = "\
++--+--+--+
+|        |
++  +  +  +
+|  |  |  |
++  +--+  +
+|       g|
++--+--+--+";
+
+    fn is_path(maze: &[Vec<char>], path: &[Cell], start: Cell, goal: Cell) -> bool {
+        path.first() == Some(&start)
+            && path.last() == Some(&goal)
+            && path.windows(2).all(|step| neighbors(maze, step[0], goal).contains(&step[1]))
+    }
+
+    #[test]
+    fn shortest_path_length() {
+        let maze = grid(FORK);
+        let path = bfs(&maze, (1, 1), (5, 8)).unwrap();
+        assert!(is_path(&maze, &path, (1, 1), (5, 8)));
+        assert_eq!(path.len(), 12);
+    }
+
+    #[test]
+    fn astar_agrees_with_bfs() {
+        let maze = grid(FORK);
+        assert_eq!(astar(&maze, (1, 1), (5, 8)).unwrap().len(), bfs(&maze, (1, 1), (5, 8)).unwrap().len());
+
+        for algorithm in Algorithm::ALL {
+            for seed in 0..3 {
+                let maze = grid(&generate(9, 7, seed, algorithm));
+                let goal = (maze.len() - 2, maze[0].len() - 2);
+                let by_bfs = bfs(&maze, (1, 1), goal).unwrap();
+                let by_astar = astar(&maze, (1, 1), goal).unwrap();
+                assert!(is_path(&maze, &by_astar, (1, 1), goal));
+                assert_eq!(by_astar.len(), by_bfs.len(), "{} seed {}", algorithm, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let maze = grid("+--+--+\n|  |  |\n+--+--+");
+        assert_eq!(bfs(&maze, (1, 1), (1, 5)), None);
+        assert_eq!(astar(&maze, (1, 1), (1, 5)), None);
+        // Fuera de la cuadrícula
+        assert_eq!(bfs(&maze, (1, 1), (9, 9)), None);
+        assert_eq!(astar(&maze, (9, 9), (1, 1)), None);
+    }
+
+    #[test]
+    fn astar_agrees_with_bfs_through_portals() {
+        // Ir directo son 19 celdas; por el portal, 7
+        let maze = grid(
+            "\
+######################
+#1                   #
+#################### #
+#################### #
+###################1 #
+######################",
+        );
+        let (start, goal) = ((1, 4), (3, 20));
+        let by_bfs = bfs(&maze, start, goal).unwrap();
+        let by_astar = astar(&maze, start, goal).unwrap();
+        assert_eq!(by_bfs.len(), 7);
+        assert_eq!(by_astar.len(), by_bfs.len());
+        assert!(is_path(&maze, &by_astar, start, goal));
+
+        // Y en el nivel de portales de la campaña, desde varias celdas
+        let level = crate::maze::load_maze(concat!(env!("CARGO_MANIFEST_DIR"), "/src/levels/02-portales.txt")).unwrap();
+        let goal = level.goal_cell();
+        for start in [(1, 1), (1, 7), (3, 4), (5, 5), (5, 10)] {
+            assert_eq!(
+                astar(&level.maze, start, goal).map(|path| path.len()),
+                bfs(&level.maze, start, goal).map(|path| path.len()),
+                "from {:?}",
+                start
+            );
+        }
+    }
+
+    #[test]
+    fn portals_lead_to_their_partner() {
+        // Las dos salas solo se comunican por el portal '1'
+        let maze = grid("+--+--+--+\n|1       |\n+--+--+--+\n|1      g|\n+--+--+--+");
+        let goal = (3, 8);
+        for path in [bfs(&maze, (1, 7), goal).unwrap(), astar(&maze, (1, 7), goal).unwrap()] {
+            assert!(is_path(&maze, &path, (1, 7), goal));
+            assert!(path.windows(2).any(|step| step == [(1, 1), (3, 1)]));
+        }
+    }
+
+    fn level(text: &str) -> Level {
+        let mut maze: Vec<Vec<char>> = grid(text);
+        let mut keys = Vec::new();
+        for (row, line) in maze.iter_mut().enumerate() {
+            for (col, glyph) in line.iter_mut().enumerate() {
+                if *glyph == 'k' {
+                    keys.push((row, col));
+                    *glyph = ' ';
+                }
+            }
+        }
+        let mut level = Level::new(maze, 100);
+        level.items = keys
+            .into_iter()
+            .map(|cell| crate::maze::Item { kind: ItemKind::Key, pos: level.cell_center(cell) })
+            .collect();
+        level
+    }
+
+    #[test]
+    fn locked_doors_need_a_key() {
+        // Sin llave no se puede abrir la puerta
+        assert_eq!(solve(&level("+--+--+\n|  L g|\n+--+--+")), None);
+        // Con la llave antes de la puerta sí
+        assert_eq!(solve(&level("+--+--+--+\n|  k L g |\n+--+--+--+")).unwrap().len(), 7);
+        // Una llave solo sirve para una puerta
+        assert_eq!(solve(&level("+--+--+--+\n| k L L g|\n+--+--+--+")), None);
+        assert!(solve(&level("+--+--+--+\n| kkL L g|\n+--+--+--+")).is_some());
+    }
+
+    #[test]
+    fn keys_behind_their_own_door_do_not_count() {
+        let maze = "\
++--+--+--+
+|     L g|
++--+  +--+
+|k L     |
++--+--+--+";
+        assert_eq!(solve(&level(maze)), None);
+    }
+
+    #[test]
+    fn detours_for_keys() {
+        // La llave está en un callejón lejos del camino directo
+        let maze = "\
++--+--+--+
+|     L g|
++--+  +--+
+|k       |
++--+--+--+";
+        let path = solve(&level(maze)).unwrap();
+        assert!(path.contains(&(3, 1)));
+        assert_eq!(path.last(), Some(&(1, 8)));
+    }
+
+    #[test]
+    fn doors_are_walkable() {
+        let maze = grid("+--+--+--+\n|  D     |\n+--+--+--+");
+        assert_eq!(bfs(&maze, (1, 1), (1, 8)).unwrap().len(), 8);
+        assert_eq!(astar(&maze, (1, 1), (1, 8)).unwrap().len(), 8);
+
+        let walled = grid("+--+--+--+\n|  |     |\n+--+--+--+");
+        assert_eq!(bfs(&walled, (1, 1), (1, 8)), None);
+    }
+}
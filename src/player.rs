@@ -1,6 +1,9 @@
 use minifb::{Window,Key};
 use nalgebra_glm::{Vec2};
+use std::f32::consts::PI;
 use crate::audio::AudioPlayer;
+use crate::maze::Level;
+use crate::maze::solve::Cell;
 
 pub struct Player {
     pub pos: Vec2,
@@ -8,34 +11,85 @@ pub struct Player {
     pub fov: f32,
     pub velocity: Vec2,
     pub previous_mouse_pos: Vec2, 
+    /// Llaves recogidas y aún sin gastar en puertas cerradas con llave.
+    pub keys: u32,
 }
 
 impl Player {
     pub fn new(pos: Vec2, a: f32, fov: f32) -> Self {
-        Self { pos, a, fov ,  velocity: Vec2::new(0.0, 0.0), previous_mouse_pos: Vec2::new(0.0, 0.0),}
+        Self { pos, a, fov ,  velocity: Vec2::new(0.0, 0.0), previous_mouse_pos: Vec2::new(0.0, 0.0), keys: 0 }
     }
 
-    pub fn player_movement(&self, new_pos: Vec2, maze: &[Vec<char>], block_size: usize) -> bool {
+    pub fn player_movement(&self, new_pos: Vec2, level: &Level) -> bool {
+        let maze = &level.maze;
+        let block_size = level.block_size;
+
+        // En niveles con bordes abiertos no se puede salir del mapa
+        if new_pos.x < 0.0 || new_pos.y < 0.0 {
+            return false;
+        }
         let row = (new_pos.y / block_size as f32).floor() as usize;
         let col = (new_pos.x / block_size as f32).floor() as usize;
 
         if row < maze.len() && col < maze[0].len() {
             let cell = maze[row][col];
-            return cell == ' ';
+            // Las puertas solo dejan pasar cuando están abiertas
+            return match level.doors.get(&(row, col)) {
+                Some(door) => door.is_open(),
+                None => cell == ' ' || level.portals.contains_key(&(row, col)),
+            };
         }
 
         false
     }
 }
 
-pub fn process_events(window: &Window, player: &mut Player, maze: &[Vec<char>], block_size: usize, audio_player: &AudioPlayer) {
-    const MOVE_SPEED: f32 = 2.0;
-    const ROTATION_SPEED: f32 = 3.14 / 80.0;
+/// Abre o cierra la puerta que el jugador tiene delante (a menos de un bloque).
+/// Una puerta con llave gasta una la primera vez; después se comporta como una normal.
+pub fn use_door(player: &mut Player, level: &mut Level) {
+    let reach = level.block_size as f32 * 0.9;
+    let target = player.pos + Vec2::new(player.a.cos(), player.a.sin()) * reach;
+    let cell = level.cell_at(target);
+    let standing_in = level.cell_at(player.pos) == cell;
+
+    let Some(door) = level.doors.get_mut(&cell) else {
+        return;
+    };
+    if door.locked {
+        if player.keys == 0 {
+            return;
+        }
+        player.keys -= 1;
+        door.locked = false;
+    }
+    // No se cierra sobre el jugador
+    if !(door.opening && standing_in) {
+        door.opening = !door.opening;
+    }
+}
+
+/// Al entrar en un portal el jugador aparece en la pareja, con la misma posición y el mismo
+/// ángulo relativos. Quien ya está dentro de un portal (recién llegado) no vuelve a saltar.
+fn through_portal(player: &mut Player, level: &Level, from: Cell) {
+    let cell = level.cell_at(player.pos);
+    if cell == from {
+        return;
+    }
+    let dir = Vec2::new(player.a.cos(), player.a.sin());
+    if let Some((pos, dir)) = level.warp(cell, player.pos, dir) {
+        player.pos = pos;
+        player.a = dir.y.atan2(dir.x);
+    }
+}
+
+pub fn process_events(window: &Window, player: &mut Player, level: &Level, audio_player: Option<&AudioPlayer>) {
+    const MOVE_SPEED: f32 = 4.0;
+    const ROTATION_SPEED: f32 = PI / 80.0;
+    const KEY_ROTATION_SPEED: f32 = PI / 40.0;
 
     let mut moved = false;
 
     if let Some((mouse_x, _mouse_y)) = window.get_mouse_pos(minifb::MouseMode::Clamp) {
-        let mouse_x = mouse_x as f32;
         let delta_x = mouse_x - player.previous_mouse_pos.x;
         if delta_x.abs() > 0.1 { 
             player.a += delta_x.signum() * ROTATION_SPEED;
@@ -46,11 +100,11 @@ pub fn process_events(window: &Window, player: &mut Player, maze: &[Vec<char>],
     let mut new_pos = player.pos;
 
     if window.is_key_down(Key::A) {
-        player.a -= ROTATION_SPEED;
+        player.a -= KEY_ROTATION_SPEED;
        
     }
     if window.is_key_down(Key::D) {
-        player.a += ROTATION_SPEED;
+        player.a += KEY_ROTATION_SPEED;
      
     }
     if window.is_key_down(Key::W) {
@@ -65,18 +119,21 @@ pub fn process_events(window: &Window, player: &mut Player, maze: &[Vec<char>],
         moved = true;
       
     }
-    if window.is_key_down(Key::P) {
-        
+
+    let walking = moved && player.player_movement(new_pos, level);
+    if walking && player.pos != new_pos {
+        let from = level.cell_at(player.pos);
+        player.pos = new_pos;
+        through_portal(player, level, from);
     }
 
-    if moved && player.player_movement(new_pos, maze, block_size) {
-        if player.pos != new_pos { 
-            player.pos = new_pos;
+    // Sin reproductor (partida sin sonido) solo se mueve
+    if let Some(audio_player) = audio_player {
+        if walking {
+            audio_player.play();
+        } else {
+            audio_player.pause();
         }
-        audio_player.play(); 
-        
-    }else{
-        audio_player.pause();
     }
     
 }
\ No newline at end of file
@@ -0,0 +1,248 @@
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, ImageResult, RgbImage};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::framebuffer::Framebuffer;
+
+/// Frames que esperan a ser codificados; si el codificador no da abasto, los siguientes se
+/// descartan en vez de frenar el juego.
+const QUEUE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// Un GIF animado que se repite.
+    Gif,
+    /// Una carpeta con 00001.png, 00002.png, ... para montarlos con otra herramienta.
+    Png,
+}
+
+impl FromStr for RecordFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "gif" => Ok(RecordFormat::Gif),
+            "png" => Ok(RecordFormat::Png),
+            _ => Err(format!("unknown recording format '{}'", s)),
+        }
+    }
+}
+
+/// Grabación en curso de los frames que se le pasan con `capture`. Se guardan como mucho
+/// `fps` frames por segundo y se codifican en otro hilo para no frenar el bucle del juego;
+/// los que no caben en la cola se cuentan en `dropped`.
+pub struct Recorder {
+    path: PathBuf,
+    interval: Duration,
+    last_frame: Option<Instant>,
+    sender: SyncSender<(RgbImage, Duration)>,
+    worker: JoinHandle<ImageResult<usize>>,
+    dropped: usize,
+}
+
+impl Recorder {
+    /// Empieza a grabar en `path`: el archivo .gif o la carpeta de la secuencia PNG.
+    pub fn start<P: Into<PathBuf>>(path: P, format: RecordFormat, fps: f32) -> io::Result<Recorder> {
+        let path = path.into();
+        match format {
+            RecordFormat::Gif => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            RecordFormat::Png => fs::create_dir_all(&path)?,
+        }
+
+        let (sender, frames) = mpsc::sync_channel(QUEUE);
+        let target = path.clone();
+        let worker = match format {
+            RecordFormat::Gif => {
+                let file = File::create(&target)?;
+                thread::spawn(move || encode_gif(file, frames))
+            }
+            RecordFormat::Png => thread::spawn(move || write_png_sequence(&target, frames)),
+        };
+
+        Ok(Recorder {
+            path,
+            interval: Duration::from_secs_f64(1.0 / f64::from(fps.max(1.0))),
+            last_frame: None,
+            sender,
+            worker,
+            dropped: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Frames descartados porque el codificador iba por detrás.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Añade el frame a la grabación, salvo que el anterior sea de hace menos de 1/fps.
+    pub fn capture(&mut self, framebuffer: &Framebuffer) {
+        self.capture_at(framebuffer, Instant::now());
+    }
+
+    fn capture_at(&mut self, framebuffer: &Framebuffer, now: Instant) {
+        // Cada frame dura lo que tardó en llegar desde el anterior, así el clip va a velocidad real
+        let delay = match self.last_frame {
+            Some(last) if now - last < self.interval => return,
+            Some(last) => now - last,
+            None => self.interval,
+        };
+
+        match self.sender.try_send((framebuffer.to_image(), delay)) {
+            Ok(()) => self.last_frame = Some(now),
+            // Sin mover `last_frame`, el siguiente frame que entre dura también lo descartado
+            Err(TrySendError::Full(_)) => self.dropped += 1,
+            // El hilo ya terminó con un error; `finish` lo informará
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    /// Termina la grabación y espera a que se escriban todos los frames; devuelve cuántos hay.
+    pub fn finish(self) -> ImageResult<usize> {
+        drop(self.sender);
+        self.worker.join().expect("recording thread panicked")
+    }
+}
+
+fn encode_gif(file: File, frames: Receiver<(RgbImage, Duration)>) -> ImageResult<usize> {
+    // Velocidad máxima de cuantización: con 256 colores por frame la diferencia apenas se nota
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), 30);
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    let mut count = 0;
+    for (image, delay) in frames {
+        let image = DynamicImage::ImageRgb8(image).into_rgba8();
+        encoder.encode_frame(Frame::from_parts(image, 0, 0, Delay::from_saturating_duration(delay)))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn write_png_sequence(dir: &Path, frames: Receiver<(RgbImage, Duration)>) -> ImageResult<usize> {
+    let mut count = 0;
+    for (image, _) in frames {
+        count += 1;
+        image.save(dir.join(format!("{:05}.png", count)))?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+    use std::io::BufReader;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("maze-recorder-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn frame() -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(4, 3);
+        framebuffer.set_background_color(0x336699);
+        framebuffer.clear();
+        framebuffer
+    }
+
+    /// Pasa frames en los milisegundos indicados desde un mismo instante.
+    fn capture_at_ms(recorder: &mut Recorder, times: &[u64]) {
+        let start = Instant::now();
+        let framebuffer = frame();
+        for &ms in times {
+            recorder.capture_at(&framebuffer, start + Duration::from_millis(ms));
+        }
+    }
+
+    #[test]
+    fn formats_parse_in_any_case() {
+        assert_eq!("GIF".parse::<RecordFormat>(), Ok(RecordFormat::Gif));
+        assert_eq!("png".parse::<RecordFormat>(), Ok(RecordFormat::Png));
+        assert!("avi".parse::<RecordFormat>().is_err());
+    }
+
+    #[test]
+    fn frames_closer_than_the_interval_are_skipped() {
+        let dir = scratch_dir("throttle");
+        let mut recorder = Recorder::start(&dir, RecordFormat::Png, 10.0).unwrap();
+
+        // A 10 fps solo entran los de 0, 100 y 250 ms
+        capture_at_ms(&mut recorder, &[0, 50, 99, 100, 180, 250]);
+        assert_eq!(recorder.finish().unwrap(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn png_sequences_are_numbered_from_one() {
+        let dir = scratch_dir("sequence");
+        let mut recorder = Recorder::start(&dir, RecordFormat::Png, 10.0).unwrap();
+        capture_at_ms(&mut recorder, &[0, 100, 200]);
+        assert_eq!(recorder.finish().unwrap(), 3);
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["00001.png", "00002.png", "00003.png"]);
+
+        let image = image::open(dir.join("00001.png")).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (4, 3));
+        assert_eq!(image.get_pixel(0, 0).0, [0x33, 0x66, 0x99]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finish_counts_the_gif_frames() {
+        let dir = scratch_dir("gif");
+        let path = dir.join("clip.gif");
+        let mut recorder = Recorder::start(&path, RecordFormat::Gif, 10.0).unwrap();
+        capture_at_ms(&mut recorder, &[0, 100]);
+        assert_eq!(recorder.finish().unwrap(), 2);
+
+        let decoder = GifDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        assert_eq!(decoder.into_frames().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_full_queue_drops_frames_instead_of_blocking() {
+        // Nadie lee la cola: se llena con QUEUE frames y el resto se descarta
+        let (sender, frames) = mpsc::sync_channel(QUEUE);
+        let mut recorder = Recorder {
+            path: PathBuf::new(),
+            interval: Duration::from_millis(100),
+            last_frame: None,
+            sender,
+            worker: thread::spawn(|| Ok(0)),
+            dropped: 0,
+        };
+
+        let times: Vec<u64> = (0..QUEUE as u64 + 3).map(|i| i * 100).collect();
+        capture_at_ms(&mut recorder, &times);
+        assert_eq!(recorder.dropped(), 3);
+
+        // El primero que vuelve a entrar dura desde el último que entró, no desde el descartado
+        assert_eq!(frames.try_iter().count(), QUEUE);
+        let start = recorder.last_frame.unwrap();
+        recorder.capture_at(&frame(), start + Duration::from_millis(300));
+        drop(recorder.sender);
+        let (_, delay) = frames.iter().last().unwrap();
+        assert_eq!(delay, Duration::from_millis(300));
+    }
+}
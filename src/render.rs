@@ -0,0 +1,523 @@
+use nalgebra_glm::Vec2;
+use once_cell::sync::Lazy;
+
+use crate::caster::{cast_ray, Face, Intersect, RayWalk};
+use crate::color::Color;
+use crate::framebuffer::{Framebuffer, Rect};
+use crate::lighting::LightMap;
+use crate::maze::{ItemKind, Level, Lighting};
+use crate::maze::solve::Cell;
+use crate::player::Player;
+use crate::texture::{LevelTextures, Texture};
+
+// Alcance máximo de los rayos en bloques en los niveles con el borde abierto; más allá una
+// columna se considera abierta
+const VIEW_DISTANCE_BLOCKS: f32 = 32.0;
+
+// Altura en pantalla de una pared a distancia d: framebuffer.height * WALL_SCALE / d
+const WALL_SCALE: f32 = 70.0;
+
+
+/// Alcance de los rayos. En un nivel cerrado cada tramo recto acaba en una pared antes de cruzar
+/// la diagonal del mapa, y hay como mucho un tramo más por cada portal; con el borde abierto se
+/// limita a `VIEW_DISTANCE_BLOCKS`.
+fn view_distance(level: &Level) -> f32 {
+    let rows = level.maze.len();
+    let cols = level.maze.first().map_or(0, |line| line.len());
+    let diagonal = ((rows * rows + cols * cols) as f32).sqrt();
+    let blocks = diagonal * (level.portals.len() + 1) as f32;
+
+    let last_row = rows.saturating_sub(1);
+    let open_border = level.maze.iter().enumerate().any(|(row, line)| {
+        line.iter().enumerate().any(|(col, &glyph)| {
+            glyph == ' ' && (row == 0 || row == last_row || col == 0 || col + 1 == line.len())
+        })
+    });
+    let blocks = if open_border { blocks.min(VIEW_DISTANCE_BLOCKS) } else { blocks };
+
+    blocks * level.block_size as f32
+}
+
+fn cell_to_texture_color(textures: &LevelTextures, cell: char, tx: u32, ty: u32) -> Color {
+    //let wall_color = 0x30822e; // Color verde oscuro para las paredes
+    let default_color = Color::BLACK;
+
+    match textures.wall(cell) {
+        Some(texture) => texture.get_scaled_pixel(tx, ty),
+        None => default_color,
+    }
+}
+
+/// Aplica la niebla del nivel a un color visto a `distance` píxeles del jugador.
+fn fog(color: Color, lighting: &Lighting, distance: f32, block_size: f32) -> Color {
+    let amount = lighting.fog.amount(distance / block_size);
+    if amount <= 0.0 {
+        color
+    } else {
+        color.mix(Color::from_hex(lighting.fog_color), amount)
+    }
+}
+
+fn draw_cell(framebuffer: &mut Framebuffer, xo: usize, yo: usize, block_size: usize, cell: char) {
+    let color = match cell {
+        'g' => 0xFF0000, // Rojo para la salida
+        _ => 0x000000,   // Negro para otras celdas
+    };
+    framebuffer.set_current_color(color);
+    framebuffer.fill_rect(xo as i32, yo as i32, block_size as i32, block_size as i32);
+}
+
+
+/// Franja de columnas de la vista 3D que dibuja un hilo. Tiene sus propios píxeles (por filas,
+/// solo de sus columnas) y al terminar se copian al framebuffer.
+struct Band {
+    x0: usize,
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+impl Band {
+    fn new(x0: usize, width: usize, height: usize) -> Self {
+        Band { x0, width, height, pixels: vec![0; width * height] }
+    }
+
+    /// `x` es la columna de la pantalla, no la de la franja.
+    fn point(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x - self.x0] = color.to_hex();
+    }
+
+    fn get_point(&self, x: usize, y: usize) -> Color {
+        Color::from_hex(self.pixels[y * self.width + x - self.x0])
+    }
+
+    fn copy_to(&self, framebuffer: &mut Framebuffer) {
+        for (y, row) in self.pixels.chunks(self.width).enumerate() {
+            let start = y * framebuffer.width + self.x0;
+            framebuffer.buffer[start..start + self.width].copy_from_slice(row);
+        }
+    }
+}
+
+/// Ángulo del rayo de la columna `i` de una pantalla de `width` columnas.
+fn column_angle(player: &Player, i: usize, width: usize) -> f32 {
+    player.a - (player.fov / 2.0) + (player.fov * (i as f32 / width as f32))
+}
+
+/// Texel de una coordenada en 0..1 de una textura de `size` píxeles. `rem_euclid` de un valor
+/// negativo muy pequeño redondea a 1.0 exacto, así que el índice se limita al último texel.
+fn texel(t: f32, size: u32) -> u32 {
+    ((t * size as f32) as u32).min(size - 1)
+}
+
+/// Suelo y techo proyectados en el mundo. Cada fila bajo el horizonte está a una distancia
+/// fija del jugador, y la fila simétrica sobre el horizonte ve el mismo punto del techo.
+/// Donde no hay techo se dibuja el cielo panorámico, que gira con `player.a`.
+fn render_floor_and_ceiling(band: &mut Band, screen_width: usize, level: &Level, textures: &LevelTextures, lights: &LightMap, player: &Player) {
+    let height = band.height;
+    let hh = height as f32 / 2.0;
+    let block_size = level.block_size as f32;
+    let sky = &textures.sky;
+    let lighting = &level.config.lighting;
+
+    for i in band.x0..band.x0 + band.width {
+        let a = column_angle(player, i, screen_width);
+        let dir = Vec2::new(a.cos(), a.sin());
+        let fisheye = (a - player.a).cos();
+        let sky_x = texel((a / std::f32::consts::TAU).rem_euclid(1.0), sky.width);
+
+        for y in (height / 2)..height {
+            // Misma proyección que las paredes: media pared (ojo al suelo) mide height * WALL_SCALE / 2 / d
+            let row_distance = height as f32 * WALL_SCALE / 2.0 / (y as f32 + 0.5 - hh);
+            let ray_distance = row_distance / fisheye;
+            let world = player.pos + dir * ray_distance;
+            let cell_x = world.x / block_size;
+            let cell_y = world.y / block_size;
+            let cell = if cell_x < 0.0 || cell_y < 0.0 {
+                (usize::MAX, usize::MAX)
+            } else {
+                (cell_y as usize, cell_x as usize)
+            };
+            let light = lights.at(world, block_size);
+            let u = cell_x.rem_euclid(1.0);
+            let v = cell_y.rem_euclid(1.0);
+
+            let floor = textures.floor_at(cell);
+            let color = floor.get_pixel(texel(u, floor.width), texel(v, floor.height));
+            band.point(i, y, fog(color.scale(light), lighting, ray_distance, block_size));
+
+            let ceiling_y = height - 1 - y;
+            let color = match textures.ceiling_at(cell) {
+                Some(ceiling) => {
+                    let color = ceiling.get_pixel(texel(u, ceiling.width), texel(v, ceiling.height));
+                    fog(color.scale(light), lighting, ray_distance, block_size)
+                }
+                // El cielo está infinitamente lejos: no le afecta la niebla
+                None => {
+                    sky.get_pixel(sky_x, texel(ceiling_y as f32 / hh, sky.height))
+                }
+            };
+            band.point(i, ceiling_y, color);
+        }
+    }
+}
+
+/// Hilos de la vista 3D: `MAZE_RENDER_THREADS` si está definida y es mayor que cero (con 1 se
+/// obtiene el renderizador secuencial para comparar), si no uno por núcleo disponible.
+static RENDER_THREADS: Lazy<usize> = Lazy::new(|| {
+    std::env::var("MAZE_RENDER_THREADS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&threads| threads > 0)
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+});
+
+/// Vista 3D. Las columnas se reparten en franjas, una por hilo; cada hilo lanza sus rayos y
+/// dibuja suelo, techo y paredes en su franja sin tocar las de los demás. Con un solo hilo la
+/// franja se dibuja en el hilo que llama, sin lanzar ninguno.
+pub fn render3d(framebuffer: &mut Framebuffer, level: &Level, textures: &LevelTextures, lights: &LightMap, player: &Player, z_buffer: &mut [f32]) {
+    debug_assert_eq!(z_buffer.len(), framebuffer.width, "one z-buffer entry per screen column");
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let threads = *RENDER_THREADS;
+    let band_width = width.div_ceil(threads).max(1);
+
+    if threads == 1 {
+        let mut band = Band::new(0, width, height);
+        render_band(&mut band, width, level, textures, lights, player, z_buffer);
+        band.copy_to(framebuffer);
+        return;
+    }
+
+    let bands: Vec<Band> = std::thread::scope(|scope| {
+        let handles: Vec<_> = z_buffer
+            .chunks_mut(band_width)
+            .enumerate()
+            .map(|(index, z_band)| {
+                scope.spawn(move || {
+                    let mut band = Band::new(index * band_width, z_band.len(), height);
+                    render_band(&mut band, width, level, textures, lights, player, z_band);
+                    band
+                })
+            })
+            .collect();
+
+        handles.into_iter().map(|handle| handle.join().expect("render thread panicked")).collect()
+    });
+
+    for band in &bands {
+        band.copy_to(framebuffer);
+    }
+}
+
+/// Suelo, techo y paredes de las columnas de una franja; `z_band` es su parte del z-buffer.
+fn render_band(band: &mut Band, screen_width: usize, level: &Level, textures: &LevelTextures, lights: &LightMap, player: &Player, z_band: &mut [f32]) {
+    let max_distance = view_distance(level);
+    let tallest = level.config.tallest_wall();
+
+    render_floor_and_ceiling(band, screen_width, level, textures, lights, player);
+
+    for (offset, depth) in z_band.iter_mut().enumerate() {
+        let i = band.x0 + offset;
+        let a = column_angle(player, i, screen_width);
+        let fisheye = (a - player.a).cos();
+
+        // Paredes de esta columna hasta la primera que tape todo lo que hay detrás;
+        // las más bajas dejan ver las de atrás por encima
+        let mut hits = Vec::new();
+        for intersect in RayWalk::new(level, player.pos, a, max_distance) {
+            let covers = level.config.wall_height(intersect.impact) >= tallest
+                && !level.config.see_through.contains(&intersect.impact);
+            hits.push(intersect);
+            if covers {
+                break;
+            }
+        }
+
+        // Los sprites se ocultan tras la primera pared maciza de altura completa,
+        // no tras barandillas ni rejas
+        *depth = hits
+            .iter()
+            .find(|intersect| {
+                level.config.wall_height(intersect.impact) >= 1.0 && !level.config.see_through.contains(&intersect.impact)
+            })
+            .map_or(f32::INFINITY, |intersect| intersect.distance * fisheye);
+
+        // De atrás hacia delante; sin paredes quedan el cielo y el suelo ya dibujados
+        for intersect in hits.iter().rev() {
+            draw_wall(band, i, level, textures, lights, fisheye, intersect);
+        }
+    }
+}
+
+/// Columna de pared de un impacto. La base queda siempre sobre el suelo y la textura se
+/// repite cada bloque de altura, empezando desde abajo. Las paredes con huecos se mezclan
+/// con lo que ya se dibujó detrás según la opacidad de su textura. `fisheye` es el coseno entre
+/// el rayo y la dirección de la vista, para corregir la distancia.
+fn draw_wall(band: &mut Band, x: usize, level: &Level, textures: &LevelTextures, lights: &LightMap, fisheye: f32, intersect: &Intersect) {
+    let lighting = &level.config.lighting;
+    let block_size = level.block_size as f32;
+    let hh = band.height as f32 / 2.0;
+
+    let distance = intersect.distance * fisheye;
+    let block_height = (band.height as f32 / distance) * WALL_SCALE;
+    let height = level.config.wall_height(intersect.impact);
+    let stake_bottom = hh + block_height / 2.0;
+    let stake_top = stake_bottom - block_height * height;
+
+    // Las caras norte/sur se ven más oscuras que las este/oeste, como en Wolfenstein
+    let brightness = match intersect.face {
+        Face::North | Face::South => lighting.side_shade,
+        Face::East | Face::West => 1.0,
+    };
+    // La luz se mide un poco delante de la pared, en la celda desde la que se ve la cara
+    let light = lights.at(intersect.hit - intersect.dir * (block_size * 0.05), block_size);
+    let fog_amount = lighting.fog.amount(intersect.distance / block_size);
+    let tx = intersect.u * 128.0;
+    let fog_color = Color::from_hex(lighting.fog_color);
+    let see_through = level.config.see_through.contains(&intersect.impact);
+
+    let first = stake_top.max(0.0) as usize;
+    let last = (stake_bottom.max(0.0) as usize).min(band.height);
+    for y in first..last {
+        // Bloques desde la base hasta esta fila
+        let above_floor = (stake_bottom - y as f32) / block_height;
+        let ty = (1.0 - above_floor).rem_euclid(1.0) * 128.0;
+        let surface = if intersect.impact == 'g' {
+            Color::from_hex(0x4c9141) // Verde para la salida
+        } else {
+            cell_to_texture_color(textures, intersect.impact, tx as u32, (ty as u32).min(127))
+        };
+        let color = surface.scale(brightness * light).mix(fog_color, fog_amount);
+        let color = if see_through {
+            if surface.a == 0 {
+                continue;
+            }
+            band.get_point(x, y).mix(color, surface.a as f32 / 255.0)
+        } else {
+            color
+        };
+        band.point(x, y, color);
+    }
+}
+
+
+pub fn render2d(framebuffer: &mut Framebuffer, level: &Level, player: &Player, hint: Option<&[Cell]>) {
+    let maze = &level.maze;
+    let block_size = level.block_size;
+    let max_distance = view_distance(level);
+
+    for (row, line) in maze.iter().enumerate() {
+        for (col, &cell) in line.iter().enumerate() {
+            draw_cell(framebuffer, col * block_size, row * block_size, block_size, cell);
+        }
+    }
+    // Migas de pan hacia la salida
+    if let Some(path) = hint {
+        framebuffer.set_current_color(0xFFD700);
+        for &cell in path {
+            let center = level.cell_center(cell);
+            framebuffer.fill_rect(center.x as i32 - 5, center.y as i32 - 5, 10, 10);
+        }
+    }
+
+    framebuffer.set_current_color(0xFFFFFF);
+    framebuffer.point(player.pos.x as usize, player.pos.y as usize);
+
+    let num_rays = 100;
+    for i in 0..num_rays {
+        let current_ray = i as f32 / num_rays as f32;
+        let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
+        let intersect = cast_ray(level, player, a, max_distance);
+        let dir = Vec2::new(a.cos(), a.sin());
+        draw_ray(framebuffer, level, player.pos, dir, intersect.as_ref().map_or(max_distance, |intersect| intersect.distance));
+
+        // Punto de impacto: amarillo en caras norte/sur, cian en este/oeste
+        if let Some(intersect) = intersect {
+            let face_color = match intersect.face {
+                Face::North | Face::South => 0xFFFF00,
+                Face::East | Face::West => 0x00FFFF,
+            };
+            framebuffer.set_current_color(face_color);
+            framebuffer.fill_rect(intersect.hit.x as i32 - 1, intersect.hit.y as i32 - 1, 3, 3);
+        }
+    }
+}
+
+/// Rayo de la vista 2D; se corta al salir del mapa.
+fn draw_ray(framebuffer: &mut Framebuffer, level: &Level, origin: Vec2, dir: Vec2, distance: f32) {
+    let width = level.maze.first().map_or(0, |line| line.len()) as f32 * level.block_size as f32;
+    let height = level.maze.len() as f32 * level.block_size as f32;
+
+    // Distancia hasta el borde del mapa en un eje
+    let exit = |origin: f32, dir: f32, size: f32| match dir {
+        _ if dir > 0.0 => (size - 1.0 - origin) / dir,
+        _ if dir < 0.0 => -origin / dir,
+        _ => f32::INFINITY,
+    };
+    let distance = distance.min(exit(origin.x, dir.x, width)).min(exit(origin.y, dir.y, height)).max(0.0);
+    let end = origin + dir * distance;
+
+    framebuffer.set_current_color(0xFFFFFF);
+    framebuffer.line(origin.x as i32, origin.y as i32, end.x as i32, end.y as i32);
+}
+
+pub fn render_minimap(framebuffer: &mut Framebuffer, level: &Level, textures: &LevelTextures, player: &Player, hint: Option<&[Cell]>) {
+    let minimap_size = 200; // Tamaño del minimapa
+    // Posición del minimapa; no se dibuja si no cabe en el framebuffer
+    let (Some(minimap_x), Some(minimap_y)) = (
+        framebuffer.width.checked_sub(minimap_size + 100),
+        framebuffer.height.checked_sub(minimap_size + 10),
+    ) else {
+        return;
+    };
+
+    // Dibujar el fondo del minimapa
+    framebuffer.set_current_color(0x222222); // Color oscuro para el fondo del minimapa
+    framebuffer.fill_rect(minimap_x as i32, minimap_y as i32, minimap_size as i32, minimap_size as i32);
+
+    let maze = &level.maze;
+    let block_size = level.block_size; // Tamaño del bloque del mapa
+    let scale = minimap_size as f32 / (maze.len() as f32 * block_size as f32);
+
+    // Dibujar el laberinto en el minimapa
+    for (row, line) in maze.iter().enumerate() {
+        for (col, &cell) in line.iter().enumerate() {
+            let cell_x = (col as f32 * block_size as f32 * scale) as usize;
+            let cell_y = (row as f32 * block_size as f32 * scale) as usize;
+            let mini_block_size = (block_size as f32 * scale) as usize;
+
+            let color = if cell == 'g' {
+                0xFF0000 // Rojo para la salida
+            } else {
+                cell_to_texture_color(textures, cell, 0, 0).to_hex()
+            };
+            framebuffer.set_current_color(color);
+            framebuffer.fill_rect((minimap_x + cell_x) as i32, (minimap_y + cell_y) as i32, mini_block_size as i32, mini_block_size as i32);
+        }
+    }
+
+    // Dibujar los objetos: llaves en amarillo, salud en verde
+    for item in &level.items {
+        framebuffer.set_current_color(match item.kind {
+            ItemKind::Key => 0xFFFF00,
+            ItemKind::Health => 0x00FF00,
+        });
+        let x = minimap_x + (item.pos.x * scale) as usize;
+        let y = minimap_y + (item.pos.y * scale) as usize;
+        framebuffer.fill_rect(x as i32, y as i32, 2, 2);
+    }
+
+    // Dibujar la pista hacia la salida
+    if let Some(path) = hint {
+        framebuffer.set_current_color(0xFFD700);
+        for &cell in path {
+            let center = level.cell_center(cell);
+            let x = minimap_x + (center.x * scale) as usize;
+            let y = minimap_y + (center.y * scale) as usize;
+            if x < framebuffer.width && y < framebuffer.height {
+                framebuffer.point(x, y);
+            }
+        }
+    }
+
+    // Dibujar la posición del jugador en el minimapa
+    framebuffer.set_current_color(0xFF0000); // Color rojo para el jugador
+    let player_x = (player.pos.x * scale) as usize;
+    let player_y = (player.pos.y * scale) as usize;
+
+    // Asegúrate de que la posición del jugador esté dentro del minimapa
+    if minimap_x + player_x < framebuffer.width && minimap_y + player_y < framebuffer.height {
+        framebuffer.point(minimap_x + player_x, minimap_y + player_y);
+    }
+}
+
+fn render_enemy(framebuffer: &mut Framebuffer, level: &Level, sprite: &Texture, lights: &LightMap, player: &Player, pos: &Vec2, z_buffer: &mut [f32]) {
+    // player_a
+    let sprite_a = (pos.y - player.pos.y).atan2(pos.x - player.pos.x);
+    // let sprite_a = - player.a;
+    //
+    if sprite_a < 0.0 {
+      return;
+    }
+  
+    let sprite_d = ((player.pos.x - pos.x).powi(2) + (player.pos.y - pos.y).powi(2)).sqrt();
+    // let sprite_d = distance(player.pos, pos);
+  
+    if sprite_d < 10.0 {
+      return;
+    }
+  
+    let light = lights.at(*pos, level.block_size as f32);
+    let screen_height = framebuffer.height as f32;
+    let screen_width = framebuffer.width as f32;
+  
+    let sprite_size = (screen_height / sprite_d) * 100.0;
+    let start_x = (sprite_a - player.a) * (screen_height / player.fov) + (screen_width / 2.0) - (sprite_size / 2.0);
+    let start_y = (screen_height / 2.0) - (sprite_size / 2.0);
+  
+    let (left, top) = (start_x, start_y);
+    let end_x = ((start_x + sprite_size) as usize).min(framebuffer.width);
+    let start_x = start_x.max(0.0) as usize;
+  
+    if end_x == 0 {
+      return;
+    }
+  
+    if start_x < framebuffer.width && sprite_d < z_buffer[start_x] {
+      let full = Rect::new(0, 0, sprite.width as i32, sprite.height as i32);
+      let dst = Rect::new(left as i32, top as i32, sprite_size as i32, sprite_size as i32);
+      framebuffer.blit_with(sprite, full, dst, |texel| {
+        // El fondo del sprite es este gris opaco
+        let color = texel.to_hex();
+        (color != 0x3a4041).then(|| {
+          fog(texel.scale(light), &level.config.lighting, sprite_d, level.block_size as f32)
+        })
+      });
+      for depth in &mut z_buffer[start_x..end_x] {
+        *depth = sprite_d;
+      }
+    }
+  }
+  
+  pub fn render_enemies(framebuffer: &mut Framebuffer, level: &Level, textures: &LevelTextures, lights: &LightMap, player: &Player, z_buffer: &mut [f32]) {
+    for enemy in &level.enemies {
+      render_enemy(framebuffer, level, &textures.enemy, lights, player, enemy, z_buffer);
+    }
+  }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::caster::cast_ray;
+    use crate::game::spawn_player;
+    use crate::maze::parse_maze;
+
+    // Un pasillo de 60 celdas con la salida al fondo
+    fn corridor(open: bool) -> Level {
+        let wall = format!("+{}+", "-".repeat(60));
+        let end = if open { ' ' } else { '|' };
+        parse_maze(&format!("{}\n|p{}g{}\n{}\n", wall, " ".repeat(58), end, wall)).unwrap()
+    }
+
+    #[test]
+    fn closed_levels_see_the_far_wall() {
+        let level = corridor(false);
+        let intersect = cast_ray(&level, &spawn_player(&level), 0.0, view_distance(&level)).unwrap();
+        assert_eq!(intersect.impact, 'g');
+    }
+
+    #[test]
+    fn texels_stay_inside_the_texture() {
+        assert_eq!(texel((-1e-9f32).rem_euclid(1.0), 64), 63);
+        assert_eq!(texel((-1e-9f32 / std::f32::consts::TAU).rem_euclid(1.0), 256), 255);
+        assert_eq!(texel(0.0, 64), 0);
+        assert_eq!(texel(0.5, 64), 32);
+    }
+
+    #[test]
+    fn open_levels_are_capped() {
+        let level = corridor(true);
+        assert_eq!(view_distance(&level), VIEW_DISTANCE_BLOCKS * level.block_size as f32);
+    }
+}
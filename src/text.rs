@@ -0,0 +1,307 @@
+use once_cell::sync::Lazy;
+use rusttype::{point, Font, Scale};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::color::Color;
+use crate::framebuffer::{Framebuffer, Rect};
+
+static SHARED: Lazy<TextRenderer> = Lazy::new(TextRenderer::new);
+
+/// Fuente cargada en un `TextRenderer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontId(usize);
+
+impl FontId {
+    /// assets/font.ttf, incluida en el ejecutable; todo `TextRenderer` la trae cargada.
+    pub const DEFAULT: FontId = FontId(0);
+}
+
+/// Dónde queda el texto respecto a la `x` que se pasa al dibujarlo.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    /// `x` es el borde izquierdo.
+    #[default]
+    Left,
+    /// `x` es el centro de cada línea.
+    Center,
+    /// `x` es el borde derecho.
+    Right,
+}
+
+/// Fuente, tamaño en píxeles, color (con alfa) y alineación con que se dibuja un texto.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub font: FontId,
+    pub size: f32,
+    pub color: Color,
+    pub align: Align,
+}
+
+impl TextStyle {
+    /// Texto blanco de la fuente por defecto, alineado a la izquierda.
+    pub fn new(size: f32) -> Self {
+        TextStyle { font: FontId::DEFAULT, size, color: Color::WHITE, align: Align::Left }
+    }
+
+    pub fn font(self, font: FontId) -> Self {
+        TextStyle { font, ..self }
+    }
+
+    pub fn color<C: Into<Color>>(self, color: C) -> Self {
+        TextStyle { color: color.into(), ..self }
+    }
+
+    pub fn align(self, align: Align) -> Self {
+        TextStyle { align, ..self }
+    }
+}
+
+/// Cobertura de un glifo rasterizado, con su esquina relativa a la línea base.
+struct Glyph {
+    left: i32,
+    top: i32,
+    width: usize,
+    coverage: Vec<u8>,
+}
+
+struct CachedFont {
+    font: Font<'static>,
+    // Glifos ya rasterizados, por carácter y tamaño (los bits del f32)
+    glyphs: Mutex<HashMap<(char, u32), Arc<Glyph>>>,
+}
+
+/// Dibuja texto con antialiasing. Cada fuente se lee una sola vez al cargarla y cada glifo se
+/// rasteriza la primera vez que se usa con un tamaño; después se copia de la caché.
+pub struct TextRenderer {
+    fonts: Vec<CachedFont>,
+}
+
+impl Default for TextRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextRenderer {
+    pub fn new() -> Self {
+        let mut renderer = TextRenderer { fonts: Vec::new() };
+        renderer
+            .add_font(include_bytes!("assets/font.ttf").to_vec())
+            .expect("embedded font is invalid");
+        renderer
+    }
+
+    /// El que usa `Framebuffer::draw_text`, compartido por todo el programa.
+    pub fn shared() -> &'static TextRenderer {
+        &SHARED
+    }
+
+    /// Añade una fuente TrueType ya leída; `None` si los datos no son una fuente.
+    pub fn add_font(&mut self, data: Vec<u8>) -> Option<FontId> {
+        let font = Font::try_from_vec(data)?;
+        self.fonts.push(CachedFont { font, glyphs: Mutex::new(HashMap::new()) });
+        Some(FontId(self.fonts.len() - 1))
+    }
+
+    pub fn load_font<P: AsRef<Path>>(&mut self, path: P) -> io::Result<FontId> {
+        let data = fs::read(&path)?;
+        self.add_font(data).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: not a TrueType font", path.as_ref().display()))
+        })
+    }
+
+    /// Distancia entre las líneas base de dos líneas seguidas.
+    pub fn line_height(&self, font: FontId, size: f32) -> f32 {
+        let metrics = self.fonts[font.0].font.v_metrics(Scale::uniform(size));
+        metrics.ascent - metrics.descent + metrics.line_gap
+    }
+
+    /// Ancho en píxeles de una línea de texto.
+    pub fn measure(&self, text: &str, font: FontId, size: f32) -> f32 {
+        self.layout(text, font, size).1
+    }
+
+    /// Dibuja una línea con la parte de arriba en `y`.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, text: &str, x: i32, y: i32, style: &TextStyle) {
+        let cached = &self.fonts[style.font.0];
+        let scale = Scale::uniform(style.size);
+        let (positions, width) = self.layout(text, style.font, style.size);
+
+        let left = match style.align {
+            Align::Left => x as f32,
+            Align::Center => x as f32 - width / 2.0,
+            Align::Right => x as f32 - width,
+        };
+        let baseline = y + cached.font.v_metrics(scale).ascent.round() as i32;
+
+        let mut glyphs = cached.glyphs.lock().unwrap();
+        for (c, caret) in positions {
+            let glyph = glyphs
+                .entry((c, style.size.to_bits()))
+                .or_insert_with(|| Arc::new(rasterize(&cached.font, c, scale)));
+            let origin_x = (left + caret).round() as i32 + glyph.left;
+            let origin_y = baseline + glyph.top;
+
+            for (index, &coverage) in glyph.coverage.iter().enumerate() {
+                if coverage == 0 {
+                    continue;
+                }
+                let alpha = (coverage as u32 * style.color.a as u32 / 255) as u8;
+                let (gx, gy) = ((index % glyph.width) as i32, (index / glyph.width) as i32);
+                framebuffer.blend_pixel(origin_x + gx, origin_y + gy, style.color.with_alpha(alpha));
+            }
+        }
+    }
+
+    /// Dibuja el texto repartido en líneas que caben en el ancho de `area`, cortando entre
+    /// palabras (y en cada salto de línea). Las líneas que no caben en su alto no se dibujan.
+    /// Devuelve el alto en píxeles de lo dibujado.
+    pub fn draw_wrapped(&self, framebuffer: &mut Framebuffer, text: &str, area: Rect, style: &TextStyle) -> i32 {
+        let line_height = self.line_height(style.font, style.size).ceil() as i32;
+        let x = match style.align {
+            Align::Left => area.x,
+            Align::Center => area.x + area.width / 2,
+            Align::Right => area.x + area.width,
+        };
+
+        let mut y = area.y;
+        for line in self.wrap(text, style.font, style.size, area.width as f32) {
+            if y + line_height > area.y + area.height {
+                break;
+            }
+            self.draw(framebuffer, &line, x, y, style);
+            y += line_height;
+        }
+        y - area.y
+    }
+
+    /// Líneas de como mucho `width` píxeles; una palabra más ancha que eso va sola en su línea.
+    pub fn wrap(&self, text: &str, font: FontId, size: f32, width: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+                if !line.is_empty() && self.measure(&candidate, font, size) > width {
+                    lines.push(std::mem::replace(&mut line, word.to_string()));
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Posición horizontal de cada carácter desde el inicio de la línea, con el kerning de la
+    /// fuente, y el ancho total.
+    fn layout(&self, text: &str, font: FontId, size: f32) -> (Vec<(char, f32)>, f32) {
+        let font = &self.fonts[font.0].font;
+        let scale = Scale::uniform(size);
+
+        let mut positions = Vec::with_capacity(text.len());
+        let mut caret = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            let glyph = font.glyph(c);
+            if let Some(previous) = previous {
+                caret += font.pair_kerning(scale, previous, glyph.id());
+            }
+            previous = Some(glyph.id());
+            positions.push((c, caret));
+            caret += glyph.scaled(scale).h_metrics().advance_width;
+        }
+        (positions, caret)
+    }
+}
+
+fn rasterize(font: &Font, c: char, scale: Scale) -> Glyph {
+    let glyph = font.glyph(c).scaled(scale).positioned(point(0.0, 0.0));
+    let Some(bounds) = glyph.pixel_bounding_box() else {
+        // Espacios y caracteres sin dibujo
+        return Glyph { left: 0, top: 0, width: 1, coverage: Vec::new() };
+    };
+
+    let width = bounds.width() as usize;
+    let mut coverage = vec![0; width * bounds.height() as usize];
+    glyph.draw(|x, y, value| {
+        coverage[y as usize * width + x as usize] = (value * 255.0).round() as u8;
+    });
+    Glyph { left: bounds.min.x, top: bounds.min.y, width, coverage }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: f32 = 20.0;
+
+    fn wrap(renderer: &TextRenderer, text: &str, width: f32) -> Vec<String> {
+        renderer.wrap(text, FontId::DEFAULT, SIZE, width)
+    }
+
+    #[test]
+    fn wraps_at_the_width_limit() {
+        let renderer = TextRenderer::new();
+        let width = renderer.measure("uno dos", FontId::DEFAULT, SIZE);
+
+        assert_eq!(wrap(&renderer, "uno dos", width), ["uno dos"]);
+        assert_eq!(wrap(&renderer, "uno dos", width - 1.0), ["uno", "dos"]);
+        assert_eq!(wrap(&renderer, "uno dos tres", width), ["uno dos", "tres"]);
+        for line in wrap(&renderer, "uno dos tres cuatro cinco seis", width) {
+            assert!(renderer.measure(&line, FontId::DEFAULT, SIZE) <= width, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn long_words_get_their_own_line() {
+        let renderer = TextRenderer::new();
+        let width = renderer.measure("a b", FontId::DEFAULT, SIZE);
+
+        assert_eq!(wrap(&renderer, "electroencefalograma", width), ["electroencefalograma"]);
+        assert_eq!(wrap(&renderer, "a electroencefalograma b", width), ["a", "electroencefalograma", "b"]);
+    }
+
+    #[test]
+    fn explicit_newlines() {
+        let renderer = TextRenderer::new();
+        assert_eq!(wrap(&renderer, "uno\ndos", 1000.0), ["uno", "dos"]);
+        assert_eq!(wrap(&renderer, "uno\n\ndos", 1000.0), ["uno", "", "dos"]);
+        assert_eq!(wrap(&renderer, "  uno   dos  ", 1000.0), ["uno dos"]);
+    }
+
+    /// Columnas que tienen algún píxel dibujado, como (primera, última).
+    fn painted_columns(framebuffer: &Framebuffer) -> (i32, i32) {
+        let image = framebuffer.to_image();
+        let columns: Vec<i32> = (0..image.width())
+            .filter(|&x| (0..image.height()).any(|y| image.get_pixel(x, y).0 != [0, 0, 0]))
+            .map(|x| x as i32)
+            .collect();
+        (*columns.first().expect("nothing was drawn"), *columns.last().unwrap())
+    }
+
+    #[test]
+    fn measure_matches_what_draw_covers() {
+        let renderer = TextRenderer::new();
+        let text = "Nivel completado";
+        let width = renderer.measure(text, FontId::DEFAULT, SIZE);
+
+        for (align, x, left) in [
+            (Align::Left, 20, 20.0),
+            (Align::Center, 150, 150.0 - width / 2.0),
+            (Align::Right, 280, 280.0 - width),
+        ] {
+            let mut framebuffer = Framebuffer::new(300, 40);
+            renderer.draw(&mut framebuffer, text, x, 5, &TextStyle::new(SIZE).align(align));
+
+            // Los glifos pueden asomar un par de píxeles fuera de su avance
+            let (first, last) = painted_columns(&framebuffer);
+            assert!((first as f32 - left).abs() <= 3.0, "{:?}: starts at {}, expected {}", align, first, left);
+            assert!((last as f32 + 1.0 - (left + width)).abs() <= 3.0, "{:?}: ends at {}, expected {}", align, last, left + width);
+        }
+    }
+}
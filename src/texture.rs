@@ -1,7 +1,16 @@
 extern crate image;
 
-use image::{ImageReader, Pixel};
+use image::{ImageReader, ImageResult};
 use image::{DynamicImage, GenericImageView};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::maze::{LevelConfig, MazeError};
+use crate::maze::solve::Cell;
+
+/// Magenta puro: en texturas sin canal alfa marca los huecos transparentes.
+pub const COLOR_KEY: u32 = 0xFF00FF;
 
 pub struct Texture {
   image: DynamicImage,
@@ -10,18 +19,175 @@ pub struct Texture {
 }
 
 impl Texture {
-  pub fn new(file_path: &str) -> Texture {
-    let img = ImageReader::open(file_path).unwrap().decode().unwrap();
+  pub fn new(file_path: &str) -> ImageResult<Texture> {
+    let img = ImageReader::open(file_path)?.decode()?;
     let width = img.width();
     let height = img.height();
-    Texture { image: img, width, height }
+    Ok(Texture { image: img, width, height })
+  }
+
+  /// Píxel con su canal alfa; el color clave `COLOR_KEY` sale con alfa 0.
+  pub fn get_pixel(&self, x: u32, y: u32) -> Color {
+    let color = Color::from(self.image.get_pixel(x, y));
+    if color.to_hex() == COLOR_KEY {
+      color.with_alpha(0)
+    } else {
+      color
+    }
+  }
+
+  /// Como `get_pixel`, en coordenadas normalizadas a 0..128, el espacio que usan `cast_ray` y
+  /// `render3d`, para que texturas de cualquier tamaño se puedan asignar a un glifo.
+  pub fn get_scaled_pixel(&self, tx: u32, ty: u32) -> Color {
+    self.get_pixel(scaled(tx, self.width), scaled(ty, self.height))
+  }
+}
+
+/// Pasa una coordenada de 0..128 a 0..size.
+fn scaled(t: u32, size: u32) -> u32 {
+  (t * size / 128).min(size - 1)
+}
+
+/// Texturas declaradas por la configuración de un nivel; cada archivo se carga una sola vez.
+pub struct LevelTextures {
+  walls: HashMap<char, Arc<Texture>>,
+  pub sky: Arc<Texture>,
+  pub floor: Arc<Texture>,
+  pub ceiling: Option<Arc<Texture>>,
+  pub enemy: Arc<Texture>,
+  // Suelos y techos por celda de las regiones del nivel, indexados por (fila, columna)
+  cell_floors: Vec<Vec<Option<Arc<Texture>>>>,
+  cell_ceilings: Vec<Vec<Option<Arc<Texture>>>>,
+}
+
+impl LevelTextures {
+  /// Falla con `MazeError::Asset` en la primera imagen que no existe o no se puede decodificar.
+  pub fn load(config: &LevelConfig) -> Result<LevelTextures, MazeError> {
+    let mut cache: HashMap<String, Arc<Texture>> = HashMap::new();
+    let mut load = |path: &str| -> Result<Arc<Texture>, MazeError> {
+      if let Some(texture) = cache.get(path) {
+        return Ok(texture.clone());
+      }
+      let texture = Arc::new(Texture::new(path).map_err(|err| MazeError::Asset {
+        path: path.to_string(),
+        message: err.to_string(),
+      })?);
+      cache.insert(path.to_string(), texture.clone());
+      Ok(texture)
+    };
+
+    let walls = config
+      .textures
+      .iter()
+      .map(|(&glyph, path)| Ok((glyph, load(path)?)))
+      .collect::<Result<_, MazeError>>()?;
+    let sky = load(&config.sky)?;
+    let floor = load(&config.floor)?;
+    let ceiling = config.ceiling.as_deref().map(&mut load).transpose()?;
+    let enemy = load(&config.enemy_sprite)?;
+
+    let rows = config.regions.iter().map(|region| region.row + region.height).max().unwrap_or(0);
+    let cols = config.regions.iter().map(|region| region.col + region.width).max().unwrap_or(0);
+    let mut cell_floors = vec![vec![None; cols]; rows];
+    let mut cell_ceilings = vec![vec![None; cols]; rows];
+
+    for region in &config.regions {
+      for row in region.row..region.row + region.height {
+        for col in region.col..region.col + region.width {
+          if let Some(path) = &region.floor {
+            cell_floors[row][col] = Some(load(path)?);
+          }
+          if let Some(path) = &region.ceiling {
+            cell_ceilings[row][col] = Some(load(path)?);
+          }
+        }
+      }
+    }
+
+    Ok(LevelTextures { walls, sky, floor, ceiling, enemy, cell_floors, cell_ceilings })
+  }
+
+  /// Textura del suelo en una celda; fuera de las regiones se usa el suelo del nivel.
+  pub fn floor_at(&self, cell: Cell) -> &Texture {
+    match self.cell_floors.get(cell.0).and_then(|row| row.get(cell.1)) {
+      Some(Some(texture)) => texture,
+      _ => &self.floor,
+    }
   }
 
-  pub fn get_pixel_color(&self, x: u32, y: u32) -> u32 {
-    let pixel = self.image.get_pixel(x, y).to_rgb();
-    let r = pixel[0];
-    let g = pixel[1];
-    let b = pixel[2];
-    ((r as u32) << 16) | ((g as u32) << 8) | (b as u32)
+  /// Textura del techo en una celda, o `None` si ahí se ve el cielo.
+  pub fn ceiling_at(&self, cell: Cell) -> Option<&Texture> {
+    match self.cell_ceilings.get(cell.0).and_then(|row| row.get(cell.1)) {
+      Some(Some(texture)) => Some(texture),
+      _ => self.ceiling.as_deref(),
+    }
   }
-}
\ No newline at end of file
+
+  pub fn wall(&self, glyph: char) -> Option<&Texture> {
+    self.walls.get(&glyph).map(|texture| texture.as_ref())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Ruta absoluta de un archivo de src/; cambiar el directorio de trabajo afectaría a las
+  /// demás pruebas, que corren en paralelo en el mismo proceso.
+  fn src(path: &str) -> String {
+    format!("{}/src/{}", env!("CARGO_MANIFEST_DIR"), path)
+  }
+
+  /// La configuración por defecto con todas sus rutas absolutas.
+  fn config() -> LevelConfig {
+    let mut config = LevelConfig::default();
+    for path in config.textures.values_mut() {
+      *path = src(path);
+    }
+    config.sky = src(&config.sky);
+    config.floor = src(&config.floor);
+    config.enemy_sprite = src(&config.enemy_sprite);
+    config
+  }
+
+  #[test]
+  fn loads_the_default_textures() {
+    let textures = LevelTextures::load(&config()).unwrap();
+    assert!(textures.wall('+').is_some());
+    assert!(textures.enemy.width > 0);
+  }
+
+  #[test]
+  fn scaled_coordinates_cover_the_whole_texture() {
+    assert_eq!(scaled(0, 64), 0);
+    assert_eq!(scaled(64, 64), 32);
+    assert_eq!(scaled(127, 64), 63);
+    assert_eq!(scaled(127, 512), 508);
+    // Más allá de 128 se queda en el último texel
+    assert_eq!(scaled(300, 64), 63);
+  }
+
+  #[test]
+  fn missing_texture_reports_its_path() {
+    let mut config = config();
+    let missing = src("assets/no-existe.png");
+    config.textures.insert('o', missing.clone());
+
+    match LevelTextures::load(&config) {
+      Err(MazeError::Asset { path, .. }) => assert_eq!(path, missing),
+      Err(err) => panic!("unexpected error: {}", err),
+      Ok(_) => panic!("missing texture was accepted"),
+    }
+  }
+
+  #[test]
+  fn corrupt_texture_reports_its_path() {
+    // Un archivo que existe pero no es una imagen
+    let maze = src("maze.txt");
+    let config = LevelConfig { floor: maze.clone(), ..config() };
+
+    let err = LevelTextures::load(&config).err().expect("maze.txt is not an image");
+    assert!(matches!(err, MazeError::Asset { ref path, .. } if *path == maze));
+    assert!(err.to_string().starts_with(&format!("cannot load '{}': ", maze)));
+  }
+}
@@ -0,0 +1,116 @@
+//! Compara frames dibujados sin ventana con las imágenes de referencia de tests/golden.
+//! Con `UPDATE_GOLDEN=1 cargo test --test golden` se vuelven a generar las referencias;
+//! si un frame no coincide, el resultado se guarda junto a los artefactos de cargo para verlo.
+
+use std::f32::consts::PI;
+use std::path::PathBuf;
+
+use maze_project::framebuffer::Framebuffer;
+use maze_project::lighting::LightMap;
+use maze_project::maze::{load_maze, Level};
+use maze_project::player::Player;
+use maze_project::render::{render2d, render3d, render_enemies, render_minimap};
+use maze_project::texture::LevelTextures;
+
+const WIDTH: usize = 800;
+const HEIGHT: usize = 600;
+
+/// Diferencia máxima por canal que no cuenta como píxel distinto (redondeos de coma flotante).
+const CHANNEL_TOLERANCE: u8 = 16;
+/// Fracción de píxeles distintos que se admite antes de dar el frame por roto.
+const PIXEL_TOLERANCE: f32 = 0.005;
+
+enum View {
+    TwoD,
+    ThreeD,
+}
+
+/// Las rutas de los niveles y texturas son relativas a src/, igual que al jugar.
+fn load(file: &str) -> Level {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap();
+    load_maze(file).unwrap_or_else(|err| panic!("{}: {}", file, err))
+}
+
+fn render(level: &Level, player: &Player, view: View) -> Framebuffer {
+    let textures = LevelTextures::load(&level.config).unwrap_or_else(|err| panic!("{}", err));
+    let lights = LightMap::new(level);
+
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    framebuffer.set_background_color(0x333355);
+    framebuffer.clear();
+
+    match view {
+        View::TwoD => render2d(&mut framebuffer, level, player, None),
+        View::ThreeD => {
+            let mut z_buffer = vec![f32::INFINITY; WIDTH];
+            render3d(&mut framebuffer, level, &textures, &lights, player, &mut z_buffer);
+            render_enemies(&mut framebuffer, level, &textures, &lights, player, &mut z_buffer);
+        }
+    }
+    render_minimap(&mut framebuffer, level, &textures, player, None);
+    framebuffer
+}
+
+fn assert_golden(name: &str, framebuffer: &Framebuffer) {
+    let actual = framebuffer.to_image();
+    let golden_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&golden_path).unwrap();
+        return;
+    }
+
+    let expected = image::open(&golden_path)
+        .unwrap_or_else(|err| panic!("{}: {} (run with UPDATE_GOLDEN=1 to create it)", golden_path.display(), err))
+        .to_rgb8();
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{}: size changed", name);
+
+    let different = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+        .count();
+    let fraction = different as f32 / (WIDTH * HEIGHT) as f32;
+
+    if fraction > PIXEL_TOLERANCE {
+        let failure_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.png", name));
+        actual.save(&failure_path).unwrap();
+        panic!(
+            "{}: {:.2}% of pixels differ from {}, frame saved to {}",
+            name,
+            fraction * 100.0,
+            golden_path.display(),
+            failure_path.display()
+        );
+    }
+}
+
+fn spawn(level: &Level) -> Player {
+    Player::new(level.spawn, level.spawn_angle, PI / 3.0)
+}
+
+#[test]
+fn maze_3d() {
+    let level = load("maze.txt");
+    assert_golden("maze_3d", &render(&level, &spawn(&level), View::ThreeD));
+}
+
+#[test]
+fn maze_2d() {
+    let level = load("maze.txt");
+    assert_golden("maze_2d", &render(&level, &spawn(&level), View::TwoD));
+}
+
+#[test]
+fn patio_3d() {
+    let level = load("levels/01-patio.txt");
+    assert_golden("patio_3d", &render(&level, &spawn(&level), View::ThreeD));
+}
+
+#[test]
+fn portals_3d() {
+    // Mirando al portal del oeste, que muestra la sala de abajo
+    let level = load("levels/02-portales.txt");
+    let player = Player::new(level.cell_center((3, 6)), PI, PI / 3.0);
+    assert_golden("portals_3d", &render(&level, &player, View::ThreeD));
+}